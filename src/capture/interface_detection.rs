@@ -1,8 +1,10 @@
-use std::process::Command;
 use std::collections::HashMap;
-use log::{debug, info, warn, error};
+use log::info;
+use serde::Serialize;
 use thiserror::Error;
 
+use super::routing::{self, RoutingBackend};
+
 #[derive(Error, Debug)]
 pub enum InterfaceDetectionError {
     #[error("Failed to execute command: {0}")]
@@ -15,7 +17,7 @@ pub enum InterfaceDetectionError {
     InterfaceNotFound(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct RouteInfo {
     pub destination: String,
     pub gateway: String,
@@ -64,143 +66,113 @@ impl InterfaceDetector {
         Ok(active_interfaces)
     }
     
-    /// Predict which interface a destination will use based on routing table
+    /// Predict which interface a destination will use based on routing table,
+    /// using real longest-prefix-match forwarding over the parsed routes.
     pub fn predict_interface_for_destination(destination: &str) -> Result<String, InterfaceDetectionError> {
         let routes = Self::get_routing_table()?;
-        
-        // For HTTP traffic, we often don't know the exact destination
-        // So we'll use some heuristics:
-        
-        // 1. If it's a specific IP, try to match routing table entries
-        if Self::is_ip_address(destination) {
+
+        if let Some((target, _, target_is_v4)) = Self::parse_network(destination) {
+            let mut best: Option<(u8, &RouteInfo)> = None;
+
             for route in &routes {
-                if Self::matches_route(destination, &route.destination) {
-                    info!("Found matching route for {}: via {}", destination, route.interface);
-                    return Ok(route.interface.clone());
+                let Some((network, prefix_len, route_is_v4)) = Self::route_network(&route.destination) else {
+                    continue;
+                };
+
+                if route_is_v4 != target_is_v4 {
+                    continue; // never match the wrong address family, even for a /0 default route
+                }
+
+                if !Self::network_matches(target, network, prefix_len) {
+                    continue;
+                }
+
+                if best.map_or(true, |(best_len, _)| prefix_len > best_len) {
+                    best = Some((prefix_len, route));
                 }
             }
+
+            if let Some((prefix_len, route)) = best {
+                info!(
+                    "Found matching route for {}: via {} (/{})",
+                    destination, route.interface, prefix_len
+                );
+                return Ok(route.interface.clone());
+            }
         }
-        
-        // 2. Fall back to default route
+
+        // Fall back to the default route
         Self::get_default_interface()
     }
-    
-    /// Get the routing table from the system
-    fn get_routing_table() -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
-        // On macOS, use 'netstat -rn' to get routing table
-        let output = Command::new("netstat")
-            .args(["-rn", "-f", "inet"])
-            .output()
-            .map_err(|e| InterfaceDetectionError::CommandFailed(format!("netstat failed: {}", e)))?;
-        
-        if !output.status.success() {
-            return Err(InterfaceDetectionError::CommandFailed(
-                "netstat command failed".to_string()
-            ));
-        }
-        
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        debug!("Routing table output:\n{}", output_str);
-        
-        Self::parse_macos_routing_table(&output_str)
-    }
-    
-    /// Parse macOS routing table output
-    fn parse_macos_routing_table(output: &str) -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
-        let mut routes = Vec::new();
-        let mut parsing_routes = false;
-        
-        for line in output.lines() {
-            let line = line.trim();
-            
-            // Skip until we reach the routing table section
-            if line.starts_with("Destination") {
-                parsing_routes = true;
-                continue;
-            }
-            
-            if !parsing_routes || line.is_empty() {
-                continue;
-            }
-            
-            // Parse routing table line: Destination Gateway Flags Interface
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 4 {
-                let route = RouteInfo {
-                    destination: parts[0].to_string(),
-                    gateway: parts[1].to_string(),
-                    flags: parts[2].to_string(),
-                    interface: parts[3].to_string(),
-                };
-                routes.push(route);
-            }
+
+    /// Parse a destination into a (network address, prefix length, is_ipv4)
+    /// triple. Accepts `default`, a bare address (treated as a /32 or /128
+    /// host route), or `addr/prefixlen`.
+    fn parse_network(destination: &str) -> Option<(u128, u8, bool)> {
+        if destination == "default" {
+            return Some((0, 0, true));
         }
-        
-        if routes.is_empty() {
-            return Err(InterfaceDetectionError::RoutingTableParse);
+
+        let (addr_part, prefix_part) = match destination.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (destination, None),
+        };
+
+        let addr: std::net::IpAddr = addr_part.parse().ok()?;
+        let is_v4 = addr.is_ipv4();
+        let max_len = if is_v4 { 32 } else { 128 };
+
+        let prefix_len = match prefix_part {
+            Some(p) => p.parse::<u8>().ok()?,
+            None => max_len,
+        };
+
+        if prefix_len > max_len {
+            return None;
         }
-        
-        debug!("Parsed {} routes", routes.len());
-        Ok(routes)
-    }
-    
-    /// Check if a string looks like an IP address
-    fn is_ip_address(addr: &str) -> bool {
-        addr.chars().any(|c| c.is_ascii_digit()) && addr.contains('.')
+
+        Some((Self::addr_to_u128(addr), prefix_len, is_v4))
     }
-    
-    /// Check if a destination matches a routing table entry
-    fn matches_route(destination: &str, route_dest: &str) -> bool {
-        // Simple matching for now - could be enhanced with subnet matching
-        if route_dest == "default" {
-            return true;
+
+    /// Parse a route's destination column the same way, defaulting bare
+    /// addresses without a slash to a /32 or /128 host route, except a bare
+    /// `0.0.0.0`, which is taken as the /0 default route.
+    fn route_network(route_dest: &str) -> Option<(u128, u8, bool)> {
+        if route_dest == "0.0.0.0" || route_dest.starts_with("0.0.0.0/0") {
+            return Some((0, 0, true));
         }
-        
-        // Direct match
-        if destination == route_dest {
-            return true;
+        Self::parse_network(route_dest)
+    }
+
+    /// Left-justify the address into a 128-bit field (IPv4 occupies the top
+    /// 32 bits) so that a `/N` mask works the same way regardless of family.
+    fn addr_to_u128(addr: std::net::IpAddr) -> u128 {
+        match addr {
+            std::net::IpAddr::V4(v4) => (u32::from(v4) as u128) << 96,
+            std::net::IpAddr::V6(v6) => u128::from(v6),
         }
-        
-        // Subnet matching (simplified)
-        if route_dest.contains('/') {
-            // This is a simplified check - real implementation would need proper CIDR matching
-            let network_part = route_dest.split('/').next().unwrap_or(route_dest);
-            return destination.starts_with(&network_part[..network_part.len().saturating_sub(3)]);
+    }
+
+    /// Check whether `target` falls within `network/prefix_len`, masking both
+    /// sides to `prefix_len` bits before comparing.
+    fn network_matches(target: u128, network: u128, prefix_len: u8) -> bool {
+        if prefix_len == 0 {
+            return true; // default route always matches
         }
-        
-        false
+
+        let mask = !0u128 << (128 - prefix_len as u32);
+        (target & mask) == (network & mask)
     }
     
+    /// Get the routing table from the system, via whichever `RoutingBackend`
+    /// is appropriate for the current platform.
+    pub fn get_routing_table() -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
+        routing::default_backend().get_routing_table()
+    }
+
     /// Get network interface statistics to see which ones are active
     pub fn get_interface_activity() -> HashMap<String, u64> {
-        let mut activity = HashMap::new();
-        
-        // On macOS, we can use 'netstat -i' to get interface statistics
-        if let Ok(output) = Command::new("netstat").args(["-i", "-b"]).output() {
-            if output.status.success() {
-                let output_str = String::from_utf8_lossy(&output.stdout);
-                
-                for line in output_str.lines() {
-                    if let Some(stats) = Self::parse_interface_stats_line(line) {
-                        activity.insert(stats.0, stats.1);
-                    }
-                }
-            }
-        }
-        
-        activity
-    }
-    
-    fn parse_interface_stats_line(line: &str) -> Option<(String, u64)> {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 8 {
-            // Interface name is in first column, bytes in column 6 (out) or 7 (in)
-            let interface = parts[0].to_string();
-            if let Ok(bytes_out) = parts[6].parse::<u64>() {
-                return Some((interface, bytes_out));
-            }
-        }
-        None
+        routing::default_backend().get_interface_activity()
     }
 }
 
@@ -235,4 +207,67 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_parse_network_default_route() {
+        assert_eq!(InterfaceDetector::parse_network("default"), Some((0, 0, true)));
+    }
+
+    #[test]
+    fn test_parse_network_bare_address_is_host_route() {
+        let (target, prefix_len, is_v4) = InterfaceDetector::parse_network("192.168.1.23").unwrap();
+        assert_eq!(prefix_len, 32);
+        assert!(is_v4);
+        assert_eq!(target, InterfaceDetector::addr_to_u128("192.168.1.23".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_parse_network_with_prefix() {
+        let (_, prefix_len, is_v4) = InterfaceDetector::parse_network("10.0.0.0/8").unwrap();
+        assert_eq!(prefix_len, 8);
+        assert!(is_v4);
+    }
+
+    #[test]
+    fn test_parse_network_ipv6() {
+        let (_, prefix_len, is_v4) = InterfaceDetector::parse_network("2001:db8::/32").unwrap();
+        assert_eq!(prefix_len, 32);
+        assert!(!is_v4);
+    }
+
+    #[test]
+    fn test_parse_network_rejects_prefix_longer_than_address() {
+        assert!(InterfaceDetector::parse_network("10.0.0.0/33").is_none());
+        assert!(InterfaceDetector::parse_network("2001:db8::/129").is_none());
+    }
+
+    #[test]
+    fn test_parse_network_rejects_garbage() {
+        assert!(InterfaceDetector::parse_network("not-an-address").is_none());
+    }
+
+    #[test]
+    fn test_network_matches_default_route_matches_everything() {
+        let target = InterfaceDetector::addr_to_u128("8.8.8.8".parse().unwrap());
+        assert!(InterfaceDetector::network_matches(target, 0, 0));
+    }
+
+    #[test]
+    fn test_network_matches_exact_host_route() {
+        let addr = InterfaceDetector::addr_to_u128("192.168.1.23".parse().unwrap());
+        assert!(InterfaceDetector::network_matches(addr, addr, 32));
+
+        let other = InterfaceDetector::addr_to_u128("192.168.1.24".parse().unwrap());
+        assert!(!InterfaceDetector::network_matches(other, addr, 32));
+    }
+
+    #[test]
+    fn test_network_matches_respects_prefix_boundary() {
+        let network = InterfaceDetector::addr_to_u128("192.168.1.0".parse().unwrap());
+        let inside = InterfaceDetector::addr_to_u128("192.168.1.200".parse().unwrap());
+        let outside = InterfaceDetector::addr_to_u128("192.168.2.1".parse().unwrap());
+
+        assert!(InterfaceDetector::network_matches(inside, network, 24));
+        assert!(!InterfaceDetector::network_matches(outside, network, 24));
+    }
 }
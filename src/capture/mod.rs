@@ -1,5 +1,17 @@
 pub mod pcap;
 pub mod interface_detection;
+pub mod model;
+pub mod packet;
+pub mod reassembly;
+pub mod routing;
+pub mod rules;
+pub mod socket_attribution;
 
-pub use pcap::{PcapCapture, CaptureError};
+pub use pcap::{PcapCapture, PcapOfflineCapture, CaptureError};
 pub use interface_detection::{InterfaceDetector, InterfaceDetectionError};
+pub use model::{InterfaceType, NetworkInterface, OperState};
+pub use packet::{decode_tcp_packet, DecodedPacket};
+pub use reassembly::{FlowKey, Reassembler, TcpSegment};
+pub use routing::RoutingBackend;
+pub use rules::{Action, Matcher, Rule, Routine, Verdict};
+pub use socket_attribution::{ProcessInfo, ProcessScope, SocketAttributor};
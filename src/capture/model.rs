@@ -0,0 +1,90 @@
+use serde::Serialize;
+
+use super::pcap::PcapCapture;
+use super::interface_detection::InterfaceDetector;
+
+/// Broad category of a network interface, derived from its name/flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterfaceType {
+    Ethernet,
+    Loopback,
+    Tunnel,
+    Aggregate,
+    Other,
+}
+
+impl InterfaceType {
+    /// Guess the type from the interface name, since that's all the pcap
+    /// device list reliably gives us across platforms.
+    fn guess(name: &str, is_loopback: bool) -> Self {
+        if is_loopback || name.starts_with("lo") {
+            InterfaceType::Loopback
+        } else if name.starts_with("utun") || name.starts_with("tun") || name.starts_with("tap") || name.starts_with("wg") {
+            InterfaceType::Tunnel
+        } else if name.starts_with("bond") || name.starts_with("bridge") || name.starts_with("br") {
+            InterfaceType::Aggregate
+        } else if name.starts_with("en") || name.starts_with("eth") {
+            InterfaceType::Ethernet
+        } else {
+            InterfaceType::Other
+        }
+    }
+}
+
+/// Administrative or operational state of an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OperState {
+    Up,
+    Down,
+    Unknown,
+    LowerLayerDown,
+}
+
+/// A richer model of a network interface than a bare `String`, carrying
+/// enough information to render a useful `list-interfaces` report.
+#[derive(Debug, Clone, Serialize)]
+pub struct NetworkInterface {
+    pub machine_name: String,
+    pub interface_type: InterfaceType,
+    pub admin_state: OperState,
+    pub oper_state: OperState,
+    pub enabled: bool,
+    pub description: Option<String>,
+}
+
+impl NetworkInterface {
+    /// Detect every interface the pcap device list knows about, enriched
+    /// with operational state and activity from `InterfaceDetector`.
+    pub fn detect_all() -> Vec<NetworkInterface> {
+        let devices = PcapCapture::list_devices().unwrap_or_default();
+        let active_interfaces = InterfaceDetector::get_active_interfaces().unwrap_or_default();
+
+        devices
+            .into_iter()
+            .map(|device| {
+                let is_loopback = device.flags.if_flags.contains(pcap::IfFlags::LOOPBACK);
+                let enabled = device.flags.if_flags.contains(pcap::IfFlags::UP);
+                let interface_type = InterfaceType::guess(&device.name, is_loopback);
+
+                let oper_state = if enabled && active_interfaces.contains(&device.name) {
+                    OperState::Up
+                } else if enabled {
+                    OperState::LowerLayerDown
+                } else {
+                    OperState::Down
+                };
+
+                NetworkInterface {
+                    machine_name: device.name,
+                    interface_type,
+                    admin_state: if enabled { OperState::Up } else { OperState::Down },
+                    oper_state,
+                    enabled,
+                    description: device.desc,
+                }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,278 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use pcap::Linktype;
+
+const PROTO_TCP: u8 = 6;
+
+/// A TCP segment decoded off the wire: the 4-tuple identifying its flow,
+/// the sequence/ack numbers and flags from the TCP header, and the
+/// segment's payload with every link/IP/TCP header already stripped.
+pub struct DecodedPacket<'a> {
+    pub src_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_addr: IpAddr,
+    pub dst_port: u16,
+    pub seq: u32,
+    pub ack: u32,
+    pub flags: u8,
+    pub payload: &'a [u8],
+}
+
+/// Decode one captured frame down to its TCP segment, honoring the
+/// capture's data-link framing (`Linktype`) - Ethernet (stripping any
+/// 802.1Q VLAN tag), raw IP, or Linux "cooked" capture - then the
+/// IPv4/IPv6 header (respecting IHL/options and the next-protocol field)
+/// and the TCP header (data offset, ports, seq/ack/flags). Returns `None`
+/// for anything that isn't TCP/IP, or too short to hold a full header.
+pub fn decode_tcp_packet(linktype: Linktype, frame: &[u8]) -> Option<DecodedPacket<'_>> {
+    let ip_data = strip_link_layer(linktype, frame)?;
+    if ip_data.is_empty() {
+        return None;
+    }
+
+    let (src_addr, dst_addr, protocol, header_len) = match ip_data[0] >> 4 {
+        4 => parse_ipv4_header(ip_data)?,
+        6 => parse_ipv6_header(ip_data)?,
+        _ => return None,
+    };
+
+    if protocol != PROTO_TCP {
+        return None;
+    }
+
+    parse_tcp_header(ip_data.get(header_len..)?, src_addr, dst_addr)
+}
+
+/// Strip the data-link header for the linktypes this crate actually
+/// captures with (see `PcapCapture`/`PcapOfflineCapture`).
+fn strip_link_layer(linktype: Linktype, frame: &[u8]) -> Option<&[u8]> {
+    match linktype {
+        Linktype::ETHERNET => strip_ethernet(frame),
+        Linktype::RAW => Some(frame),
+        Linktype::LINUX_SLL => frame.get(16..),
+        _ => None,
+    }
+}
+
+fn strip_ethernet(frame: &[u8]) -> Option<&[u8]> {
+    const ETH_HEADER_LEN: usize = 14;
+    const ETHERTYPE_VLAN: u16 = 0x8100;
+
+    let header = frame.get(..ETH_HEADER_LEN)?;
+    let ethertype = u16::from_be_bytes([header[12], header[13]]);
+
+    if ethertype == ETHERTYPE_VLAN {
+        // Skip the 4-byte 802.1Q tag sitting between the MACs and the real ethertype.
+        frame.get(ETH_HEADER_LEN + 4..)
+    } else {
+        frame.get(ETH_HEADER_LEN..)
+    }
+}
+
+fn parse_ipv4_header(data: &[u8]) -> Option<(IpAddr, IpAddr, u8, usize)> {
+    if data.len() < 20 {
+        return None;
+    }
+
+    let header_len = (data[0] & 0x0f) as usize * 4;
+    if data.len() < header_len {
+        return None;
+    }
+
+    let protocol = data[9];
+    let src = IpAddr::V4(Ipv4Addr::new(data[12], data[13], data[14], data[15]));
+    let dst = IpAddr::V4(Ipv4Addr::new(data[16], data[17], data[18], data[19]));
+    Some((src, dst, protocol, header_len))
+}
+
+fn parse_ipv6_header(data: &[u8]) -> Option<(IpAddr, IpAddr, u8, usize)> {
+    const IPV6_HEADER_LEN: usize = 40;
+    if data.len() < IPV6_HEADER_LEN {
+        return None;
+    }
+
+    let next_header = data[6];
+    let src = IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&data[8..24]).ok()?));
+    let dst = IpAddr::V6(Ipv6Addr::from(<[u8; 16]>::try_from(&data[24..40]).ok()?));
+    Some((src, dst, next_header, IPV6_HEADER_LEN))
+}
+
+fn parse_tcp_header(data: &[u8], src_addr: IpAddr, dst_addr: IpAddr) -> Option<DecodedPacket<'_>> {
+    if data.len() < 20 {
+        return None;
+    }
+
+    let src_port = u16::from_be_bytes([data[0], data[1]]);
+    let dst_port = u16::from_be_bytes([data[2], data[3]]);
+    let seq = u32::from_be_bytes([data[4], data[5], data[6], data[7]]);
+    let ack = u32::from_be_bytes([data[8], data[9], data[10], data[11]]);
+    let data_offset = ((data[12] >> 4) & 0x0f) as usize * 4;
+    let flags = data[13];
+
+    if data.len() < data_offset {
+        return None;
+    }
+
+    Some(DecodedPacket {
+        src_addr,
+        src_port,
+        dst_addr,
+        dst_port,
+        seq,
+        ack,
+        flags,
+        payload: &data[data_offset..],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYN: u8 = 0x02;
+
+    fn tcp_header(src_port: u16, dst_port: u16, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; 20];
+        header[0..2].copy_from_slice(&src_port.to_be_bytes());
+        header[2..4].copy_from_slice(&dst_port.to_be_bytes());
+        header[4..8].copy_from_slice(&1000u32.to_be_bytes()); // seq
+        header[8..12].copy_from_slice(&2000u32.to_be_bytes()); // ack
+        header[12] = 5 << 4; // data offset: 5 words, no options
+        header[13] = flags;
+        header.extend_from_slice(payload);
+        header
+    }
+
+    fn ipv4_packet(protocol: u8, src: [u8; 4], dst: [u8; 4], rest: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; 20];
+        header[0] = 0x45; // version 4, IHL 5 words
+        header[9] = protocol;
+        header[12..16].copy_from_slice(&src);
+        header[16..20].copy_from_slice(&dst);
+        header.extend_from_slice(rest);
+        header
+    }
+
+    fn ipv6_packet(next_header: u8, src: [u8; 16], dst: [u8; 16], rest: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; 40];
+        header[0] = 0x60; // version 6
+        header[6] = next_header;
+        header[8..24].copy_from_slice(&src);
+        header[24..40].copy_from_slice(&dst);
+        header.extend_from_slice(rest);
+        header
+    }
+
+    fn ethernet_frame(ethertype: u16, rest: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 14];
+        frame[12..14].copy_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(rest);
+        frame
+    }
+
+    fn vlan_ethernet_frame(ethertype: u16, rest: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 14];
+        frame[12..14].copy_from_slice(&0x8100u16.to_be_bytes());
+        frame.extend_from_slice(&[0x00, 0x64]); // VLAN tag: priority/id, id
+        frame.extend_from_slice(&ethertype.to_be_bytes());
+        frame.extend_from_slice(rest);
+        frame
+    }
+
+    #[test]
+    fn decodes_an_ethernet_ipv4_tcp_packet() {
+        let tcp = tcp_header(54321, 80, SYN, b"hello");
+        let ip = ipv4_packet(PROTO_TCP, [192, 168, 1, 23], [93, 184, 216, 34], &tcp);
+        let frame = ethernet_frame(0x0800, &ip);
+
+        let decoded = decode_tcp_packet(Linktype::ETHERNET, &frame).unwrap();
+        assert_eq!(decoded.src_addr, IpAddr::V4(Ipv4Addr::new(192, 168, 1, 23)));
+        assert_eq!(decoded.dst_addr, IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34)));
+        assert_eq!(decoded.src_port, 54321);
+        assert_eq!(decoded.dst_port, 80);
+        assert_eq!(decoded.flags, SYN);
+        assert_eq!(decoded.payload, b"hello");
+    }
+
+    #[test]
+    fn decodes_a_vlan_tagged_ethernet_frame() {
+        let tcp = tcp_header(54321, 80, 0, b"hi");
+        let ip = ipv4_packet(PROTO_TCP, [10, 0, 0, 1], [10, 0, 0, 2], &tcp);
+        let frame = vlan_ethernet_frame(0x0800, &ip);
+
+        let decoded = decode_tcp_packet(Linktype::ETHERNET, &frame).unwrap();
+        assert_eq!(decoded.src_port, 54321);
+        assert_eq!(decoded.payload, b"hi");
+    }
+
+    #[test]
+    fn decodes_an_ipv6_tcp_packet() {
+        let tcp = tcp_header(443, 51234, 0, b"data");
+        let src = Ipv6Addr::from([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+        let dst = Ipv6Addr::from([0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]);
+        let ip = ipv6_packet(PROTO_TCP, src.octets(), dst.octets(), &tcp);
+        let frame = ethernet_frame(0x86DD, &ip);
+
+        let decoded = decode_tcp_packet(Linktype::ETHERNET, &frame).unwrap();
+        assert_eq!(decoded.src_addr, IpAddr::V6(src));
+        assert_eq!(decoded.dst_addr, IpAddr::V6(dst));
+        assert_eq!(decoded.src_port, 443);
+        assert_eq!(decoded.payload, b"data");
+    }
+
+    #[test]
+    fn decodes_raw_linktype_without_link_header() {
+        let tcp = tcp_header(1, 2, 0, b"");
+        let ip = ipv4_packet(PROTO_TCP, [1, 1, 1, 1], [2, 2, 2, 2], &tcp);
+
+        let decoded = decode_tcp_packet(Linktype::RAW, &ip).unwrap();
+        assert_eq!(decoded.src_addr, IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)));
+    }
+
+    #[test]
+    fn decodes_linux_cooked_capture() {
+        let tcp = tcp_header(1, 2, 0, b"");
+        let ip = ipv4_packet(PROTO_TCP, [1, 1, 1, 1], [2, 2, 2, 2], &tcp);
+        let mut frame = vec![0u8; 16];
+        frame.extend_from_slice(&ip);
+
+        let decoded = decode_tcp_packet(Linktype::LINUX_SLL, &frame).unwrap();
+        assert_eq!(decoded.dst_addr, IpAddr::V4(Ipv4Addr::new(2, 2, 2, 2)));
+    }
+
+    #[test]
+    fn rejects_non_tcp_protocol() {
+        const PROTO_UDP: u8 = 17;
+        let ip = ipv4_packet(PROTO_UDP, [1, 1, 1, 1], [2, 2, 2, 2], &[0; 20]);
+        let frame = ethernet_frame(0x0800, &ip);
+
+        assert!(decode_tcp_packet(Linktype::ETHERNET, &frame).is_none());
+    }
+
+    #[test]
+    fn rejects_unsupported_linktype() {
+        let tcp = tcp_header(1, 2, 0, b"");
+        let ip = ipv4_packet(PROTO_TCP, [1, 1, 1, 1], [2, 2, 2, 2], &tcp);
+
+        assert!(decode_tcp_packet(Linktype::NULL, &ip).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_ethernet_header() {
+        let frame = vec![0u8; 10];
+        assert!(decode_tcp_packet(Linktype::ETHERNET, &frame).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_ip_header() {
+        let frame = ethernet_frame(0x0800, &[0x45, 0, 0, 0]);
+        assert!(decode_tcp_packet(Linktype::ETHERNET, &frame).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_tcp_header() {
+        let ip = ipv4_packet(PROTO_TCP, [1, 1, 1, 1], [2, 2, 2, 2], &[0; 10]);
+        let frame = ethernet_frame(0x0800, &ip);
+        assert!(decode_tcp_packet(Linktype::ETHERNET, &frame).is_none());
+    }
+}
@@ -2,6 +2,8 @@ use pcap::{Capture, Device};
 use thiserror::Error;
 use log::{info, debug, error, warn};
 
+use super::rules::Routine;
+
 #[derive(Error, Debug)]
 pub enum CaptureError {
     #[error("Failed to list network devices: {0}")]
@@ -21,6 +23,8 @@ pub enum CaptureError {
 pub struct PcapCapture {
     capture: Capture<pcap::Active>,
     interface_name: String,
+    routine: Routine,
+    savefile: Option<pcap::Savefile>,
 }
 
 impl PcapCapture {
@@ -84,8 +88,18 @@ impl PcapCapture {
         }
     }
     
-    /// Create a new packet capture instance on the specified interface
+    /// Create a new packet capture instance on the specified interface with
+    /// the default routine (accept all TCP traffic).
     pub fn new(interface_name: Option<&str>) -> Result<Self, CaptureError> {
+        Self::with_routine(interface_name, Routine::default())
+    }
+
+    /// Create a new packet capture instance on the specified interface,
+    /// applying the given rule `Routine`. Matchers that translate to BPF
+    /// (ports, hosts) are compiled into the capture's kernel-side filter;
+    /// HTTP-level matchers (method/host) are evaluated later, by
+    /// `Routine::evaluate_http`, once a message has been parsed.
+    pub fn with_routine(interface_name: Option<&str>, routine: Routine) -> Result<Self, CaptureError> {
         let device = if let Some(name) = interface_name {
             // Find specific interface by name
             let devices = Self::list_devices()?;
@@ -109,30 +123,52 @@ impl PcapCapture {
             .open()
             .map_err(CaptureError::CaptureConfig)?;
         
-        // Set BPF filter to capture only TCP traffic (HTTP runs over TCP)
-        capture.filter("tcp", true)
+        // Compile the routine's BPF-eligible matchers into the kernel-side filter
+        let filter_str = routine.to_bpf_filter();
+        capture.filter(&filter_str, true)
             .map_err(CaptureError::CaptureConfig)?;
-        
-        info!("Packet capture initialized successfully");
-        
+
+        info!("Packet capture initialized successfully with filter: {}", filter_str);
+
         Ok(Self {
             capture,
             interface_name: device.name,
+            routine,
+            savefile: None,
         })
     }
-    
+
+    /// The rule routine this capture was configured with, for evaluating
+    /// HTTP-level matchers after a message is parsed.
+    pub fn routine(&self) -> &Routine {
+        &self.routine
+    }
+
+    /// Attach a `.pcap` savefile; every packet captured from this point on
+    /// is also written to disk, so the live session can be replayed later
+    /// with `PcapOfflineCapture` (the `--write`/`--read` round trip).
+    pub fn write_to_file(&mut self, path: &str) -> Result<(), CaptureError> {
+        let savefile = self.capture.savefile(path).map_err(CaptureError::CaptureConfig)?;
+        self.savefile = Some(savefile);
+        Ok(())
+    }
+
     /// Start capturing packets and process them with the provided callback
     pub fn start_capture<F>(&mut self, mut packet_handler: F) -> Result<(), CaptureError>
     where
         F: FnMut(&[u8]) -> bool, // Return false to stop capture
     {
         info!("Starting packet capture on interface: {}", self.interface_name);
-        
+
         loop {
             match self.capture.next_packet() {
                 Ok(packet) => {
                     debug!("Captured packet: {} bytes", packet.data.len());
-                    
+
+                    if let Some(savefile) = &mut self.savefile {
+                        savefile.write(&packet);
+                    }
+
                     // Call the packet handler
                     if !packet_handler(packet.data) {
                         info!("Packet handler requested capture stop");
@@ -149,7 +185,7 @@ impl PcapCapture {
                 }
             }
         }
-        
+
         Ok(())
     }
     
@@ -163,6 +199,83 @@ impl PcapCapture {
     pub fn interface_name(&self) -> &str {
         &self.interface_name
     }
+
+    /// The data-link framing packets arrive in on this capture, so callers
+    /// can decode them (see `capture::packet::decode_tcp_packet`).
+    pub fn linktype(&self) -> pcap::Linktype {
+        self.capture.get_datalink()
+    }
+}
+
+/// Replays a previously saved `.pcap` file through the same packet pipeline
+/// used for live captures (the `--read` offline mode), so traces captured
+/// elsewhere - or with `PcapCapture::write_to_file` - can be analyzed later.
+pub struct PcapOfflineCapture {
+    capture: Capture<pcap::Offline>,
+    file_path: String,
+    routine: Routine,
+}
+
+impl PcapOfflineCapture {
+    /// Open a saved capture file, applying the given rule `Routine` the same
+    /// way a live `PcapCapture` would.
+    pub fn open(file_path: &str, routine: Routine) -> Result<Self, CaptureError> {
+        info!("Opening saved capture: {}", file_path);
+
+        let mut capture = Capture::from_file(file_path).map_err(CaptureError::CaptureOpen)?;
+
+        // Compile the routine's BPF-eligible matchers into the replay's filter
+        let filter_str = routine.to_bpf_filter();
+        capture.filter(&filter_str, true)
+            .map_err(CaptureError::CaptureConfig)?;
+
+        Ok(Self {
+            capture,
+            file_path: file_path.to_string(),
+            routine,
+        })
+    }
+
+    /// The rule routine this replay was configured with, for evaluating
+    /// HTTP-level matchers after a message is parsed.
+    pub fn routine(&self) -> &Routine {
+        &self.routine
+    }
+
+    /// The data-link framing packets were captured in, as recorded in the
+    /// saved file's header.
+    pub fn linktype(&self) -> pcap::Linktype {
+        self.capture.get_datalink()
+    }
+
+    /// Replay every packet in the file through the provided callback, until
+    /// the file is exhausted or the handler asks to stop.
+    pub fn start_capture<F>(&mut self, mut packet_handler: F) -> Result<(), CaptureError>
+    where
+        F: FnMut(&[u8]) -> bool, // Return false to stop replay
+    {
+        info!("Replaying capture from: {}", self.file_path);
+
+        loop {
+            match self.capture.next_packet() {
+                Ok(packet) => {
+                    debug!("Replaying packet: {} bytes", packet.data.len());
+
+                    if !packet_handler(packet.data) {
+                        info!("Packet handler requested replay stop");
+                        break;
+                    }
+                },
+                Err(pcap::Error::NoMorePackets) => break,
+                Err(e) => {
+                    error!("Packet replay error: {}", e);
+                    return Err(CaptureError::PacketCapture(e));
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,495 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+use log::warn;
+
+/// TCP flags relevant to reassembly bookkeeping.
+pub const TCP_FLAG_FIN: u8 = 0x01;
+pub const TCP_FLAG_SYN: u8 = 0x02;
+pub const TCP_FLAG_RST: u8 = 0x04;
+pub const TCP_FLAG_ACK: u8 = 0x10;
+
+/// The 4-tuple identifying a TCP flow, direction-independent (always stored
+/// with the lower `(addr, port)` pair first so both directions of the same
+/// connection hash to the same key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FlowKey {
+    a: (IpAddr, u16),
+    b: (IpAddr, u16),
+}
+
+impl FlowKey {
+    pub fn new(src_addr: IpAddr, src_port: u16, dst_addr: IpAddr, dst_port: u16) -> Self {
+        let src = (src_addr, src_port);
+        let dst = (dst_addr, dst_port);
+        if src <= dst {
+            FlowKey { a: src, b: dst }
+        } else {
+            FlowKey { a: dst, b: src }
+        }
+    }
+}
+
+/// One TCP segment handed to the reassembler.
+pub struct TcpSegment {
+    pub src_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_addr: IpAddr,
+    pub dst_port: u16,
+    pub seq: u32,
+    pub flags: u8,
+    pub payload: Vec<u8>,
+}
+
+impl TcpSegment {
+    /// Fallback direction heuristic for flows whose handshake `SYN` wasn't
+    /// observed (e.g. capture started mid-connection): which endpoint sorts
+    /// first in the `FlowKey`. Uncorrelated with who's actually the client,
+    /// so `FlowState::initiator` (set from the real handshake) is always
+    /// preferred when available.
+    fn sorts_first(&self, flow: &FlowKey) -> bool {
+        (self.src_addr, self.src_port) == flow.a
+    }
+}
+
+/// A reassembled, fully-framed HTTP message (headers + body already joined).
+pub struct ReassembledMessage {
+    pub is_outgoing: bool,
+    pub data: Vec<u8>,
+}
+
+/// Per-direction reassembly state: out-of-order segments buffered by sequence
+/// number until they become contiguous, then drained into `linear`.
+#[derive(Default)]
+struct DirectionState {
+    base_seq: Option<u32>,
+    out_of_order: BTreeMap<u32, Vec<u8>>,
+    linear: Vec<u8>,
+    /// Bytes already handed out as complete messages and drained from `linear`.
+    consumed: u32,
+}
+
+impl DirectionState {
+    fn ingest(&mut self, seq: u32, payload: Vec<u8>) {
+        if payload.is_empty() {
+            return;
+        }
+
+        let base = *self.base_seq.get_or_insert(seq);
+        self.out_of_order.insert(seq.wrapping_sub(base), payload);
+        self.drain_contiguous();
+    }
+
+    fn drain_contiguous(&mut self) {
+        loop {
+            let next_offset = self.consumed.wrapping_add(self.linear.len() as u32);
+            let Some((&offset, _)) = self.out_of_order.iter().next() else {
+                break;
+            };
+
+            if offset < next_offset {
+                // Retransmit overlapping data we already have; keep only any new tail.
+                let segment = self.out_of_order.remove(&offset).unwrap();
+                let overlap = (next_offset - offset) as usize;
+                if segment.len() > overlap {
+                    self.linear.extend_from_slice(&segment[overlap..]);
+                }
+                continue;
+            }
+            if offset > next_offset {
+                break; // gap - wait for the missing segment
+            }
+
+            let segment = self.out_of_order.remove(&offset).unwrap();
+            self.linear.extend_from_slice(&segment);
+        }
+    }
+}
+
+/// A flow being tracked for reassembly: one `DirectionState` per direction,
+/// plus bookkeeping for idle eviction.
+struct FlowState {
+    outgoing: DirectionState,
+    incoming: DirectionState,
+    last_seen: Instant,
+    closed: bool,
+    /// The endpoint that sent the connection's initial `SYN` (without
+    /// `ACK`), i.e. the actual client - not to be confused with whichever
+    /// endpoint happens to sort first in the `FlowKey`. Filled in the first
+    /// time that packet is seen; `None` until then (or for flows whose
+    /// handshake was missed, e.g. capture started mid-connection).
+    initiator: Option<(IpAddr, u16)>,
+}
+
+impl FlowState {
+    fn new() -> Self {
+        Self {
+            outgoing: DirectionState::default(),
+            incoming: DirectionState::default(),
+            last_seen: Instant::now(),
+            closed: false,
+            initiator: None,
+        }
+    }
+}
+
+/// Reassembles TCP byte streams into complete HTTP messages, tolerating
+/// out-of-order segments and retransmits, and evicts idle/closed flows to
+/// bound memory.
+pub struct Reassembler {
+    flows: HashMap<FlowKey, FlowState>,
+    idle_timeout: Duration,
+}
+
+impl Default for Reassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self {
+            flows: HashMap::new(),
+            idle_timeout: Duration::from_secs(90),
+        }
+    }
+
+    /// Feed one TCP segment into the reassembler, returning every complete
+    /// HTTP message that became available as a result.
+    pub fn ingest(&mut self, segment: TcpSegment) -> Vec<ReassembledMessage> {
+        let flow_key = FlowKey::new(
+            segment.src_addr,
+            segment.src_port,
+            segment.dst_addr,
+            segment.dst_port,
+        );
+        let flags = segment.flags;
+        let src = (segment.src_addr, segment.src_port);
+
+        let flow = self.flows.entry(flow_key).or_insert_with(FlowState::new);
+        flow.last_seen = Instant::now();
+
+        // The handshake's initial SYN (without ACK) identifies the actual
+        // connection initiator; prefer that over the address-ordering
+        // fallback, which has nothing to do with who's the client.
+        if flow.initiator.is_none() && flags & TCP_FLAG_SYN != 0 && flags & TCP_FLAG_ACK == 0 {
+            flow.initiator = Some(src);
+        }
+
+        let is_outgoing = match flow.initiator {
+            Some(initiator) => src == initiator,
+            None => segment.sorts_first(&flow_key),
+        };
+
+        let direction = if is_outgoing {
+            &mut flow.outgoing
+        } else {
+            &mut flow.incoming
+        };
+        if flags & TCP_FLAG_SYN != 0 {
+            // The SYN consumes one sequence number; stream data starts right after it.
+            direction.base_seq.get_or_insert(segment.seq.wrapping_add(1));
+        }
+        direction.ingest(segment.seq, segment.payload);
+
+        let mut completed = Vec::new();
+        while let Some(message) = Self::try_extract_message(direction) {
+            completed.push(ReassembledMessage { is_outgoing, data: message });
+        }
+
+        if flags & (TCP_FLAG_FIN | TCP_FLAG_RST) != 0 {
+            flow.closed = true;
+        }
+
+        completed
+    }
+
+    /// Drop flows that have been closed or idle past the timeout, to bound
+    /// memory on long-running captures.
+    pub fn evict_idle(&mut self) {
+        let idle_timeout = self.idle_timeout;
+        self.flows
+            .retain(|_, flow| !flow.closed && flow.last_seen.elapsed() < idle_timeout);
+    }
+
+    /// If `direction`'s linear buffer contains a complete HTTP message
+    /// (headers terminated by `\r\n\r\n`, with the body framed by
+    /// `Content-Length` or `Transfer-Encoding: chunked`), remove and return
+    /// it as a standalone byte buffer; otherwise leave the buffer untouched.
+    fn try_extract_message(direction: &mut DirectionState) -> Option<Vec<u8>> {
+        let header_end = find_header_end(&direction.linear)?;
+        let headers = &direction.linear[..header_end];
+
+        let body_len = match body_framing(headers)? {
+            BodyFraming::ContentLength(len) => len,
+            BodyFraming::Chunked => {
+                let chunked_len = decode_chunked_len(&direction.linear[header_end..])?;
+                chunked_len
+            }
+            BodyFraming::None => 0,
+        };
+
+        let total_len = header_end.checked_add(body_len)?;
+        if direction.linear.len() < total_len {
+            return None; // body not fully arrived yet
+        }
+
+        let message = direction.linear[..total_len].to_vec();
+        direction.linear.drain(..total_len);
+        direction.consumed = direction.consumed.wrapping_add(total_len as u32);
+        Some(message)
+    }
+}
+
+enum BodyFraming {
+    ContentLength(usize),
+    Chunked,
+    None,
+}
+
+fn find_header_end(buf: &[u8]) -> Option<usize> {
+    buf.windows(4).position(|w| w == b"\r\n\r\n").map(|pos| pos + 4)
+}
+
+fn body_framing(headers: &[u8]) -> Option<BodyFraming> {
+    let headers_str = std::str::from_utf8(headers).ok()?;
+
+    for line in headers_str.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.trim();
+        let value = value.trim();
+
+        if name.eq_ignore_ascii_case("content-length") {
+            return value.parse::<usize>().ok().map(BodyFraming::ContentLength);
+        }
+        if name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked") {
+            return Some(BodyFraming::Chunked);
+        }
+    }
+
+    Some(BodyFraming::None)
+}
+
+/// Walk chunked-encoding framing (without decoding it) to find how many bytes
+/// after the headers make up the complete chunked body, including the
+/// terminating `0\r\n\r\n` chunk. Returns `None` until the terminator has
+/// fully arrived.
+fn decode_chunked_len(body: &[u8]) -> Option<usize> {
+    let mut offset = 0;
+
+    loop {
+        let line_end = body[offset..].windows(2).position(|w| w == b"\r\n")? + offset;
+        let size_line = std::str::from_utf8(&body[offset..line_end]).ok()?;
+        let chunk_size = usize::from_str_radix(size_line.trim(), 16).ok()?;
+
+        let chunk_start = line_end + 2;
+        if chunk_size == 0 {
+            // Final chunk: terminated by its own trailing CRLF.
+            let terminator_end = chunk_start.checked_add(2)?;
+            if body.len() < terminator_end {
+                return None;
+            }
+            return Some(terminator_end);
+        }
+
+        // A bogus/hostile chunk-size line (e.g. near `usize::MAX`) must not be
+        // allowed to overflow this arithmetic; treat it as malformed framing.
+        let chunk_end = chunk_start.checked_add(chunk_size)?.checked_add(2)?; // +2 for the trailing CRLF
+        if body.len() < chunk_end {
+            return None;
+        }
+        offset = chunk_end;
+    }
+}
+
+/// Decode a fully-buffered chunked body into its concatenated payload bytes.
+pub fn decode_chunked_body(body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut offset = 0;
+
+    loop {
+        let line_end = body[offset..].windows(2).position(|w| w == b"\r\n")? + offset;
+        let size_line = std::str::from_utf8(&body[offset..line_end]).ok()?;
+        let chunk_size = usize::from_str_radix(size_line.trim(), 16).ok()?;
+
+        let chunk_start = line_end + 2;
+        if chunk_size == 0 {
+            return Some(decoded);
+        }
+
+        // Guard against a bogus/hostile chunk-size line overflowing this
+        // arithmetic instead of just failing the bounds check below.
+        let Some(chunk_end) = chunk_start.checked_add(chunk_size) else {
+            warn!("Rejecting chunked body with an out-of-range chunk size");
+            return Some(decoded);
+        };
+        let Some(chunk_end_with_crlf) = chunk_end.checked_add(2) else {
+            warn!("Rejecting chunked body with an out-of-range chunk size");
+            return Some(decoded);
+        };
+        if body.len() < chunk_end_with_crlf {
+            warn!("Truncated chunked body while decoding");
+            return Some(decoded);
+        }
+
+        decoded.extend_from_slice(&body[chunk_start..chunk_end]);
+        offset = chunk_end + 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    fn addr(octet: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(10, 0, 0, octet))
+    }
+
+    fn segment(src: IpAddr, src_port: u16, dst: IpAddr, dst_port: u16, seq: u32, flags: u8, payload: &[u8]) -> TcpSegment {
+        TcpSegment {
+            src_addr: src,
+            src_port,
+            dst_addr: dst,
+            dst_port,
+            seq,
+            flags,
+            payload: payload.to_vec(),
+        }
+    }
+
+    #[test]
+    fn reassembles_a_single_segment_request() {
+        let mut reassembler = Reassembler::new();
+        let client = addr(1);
+        let server = addr(2);
+
+        let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let messages = reassembler.ingest(segment(client, 54321, server, 80, 1000, 0, request));
+
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].is_outgoing);
+        assert_eq!(messages[0].data, request);
+    }
+
+    #[test]
+    fn reassembles_out_of_order_segments() {
+        let mut reassembler = Reassembler::new();
+        let client = addr(1);
+        let server = addr(2);
+
+        let request = b"GET /path HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let (first_half, second_half) = request.split_at(10);
+
+        // The handshake SYN fixes the stream's base: data starts at seq 1000.
+        reassembler.ingest(segment(client, 54321, server, 80, 999, TCP_FLAG_SYN, b""));
+
+        // Second half arrives first; it should be buffered, not dropped.
+        let early = reassembler.ingest(segment(client, 54321, server, 80, 1010, 0, second_half));
+        assert!(early.is_empty());
+
+        let messages = reassembler.ingest(segment(client, 54321, server, 80, 1000, 0, first_half));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].data, request);
+    }
+
+    #[test]
+    fn direction_follows_the_handshake_initiator_not_address_order() {
+        // The client's address numerically sorts after the server's here,
+        // which used to fool the old tuple-ordering heuristic into calling
+        // the server "outgoing" and the client "incoming".
+        let mut reassembler = Reassembler::new();
+        let client = addr(200);
+        let server = addr(1);
+
+        reassembler.ingest(segment(client, 54321, server, 80, 999, TCP_FLAG_SYN, b""));
+
+        let request = b"GET / HTTP/1.1\r\nHost: example.com\r\n\r\n";
+        let messages = reassembler.ingest(segment(client, 54321, server, 80, 1000, 0, request));
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].is_outgoing);
+
+        let response = b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n";
+        let messages = reassembler.ingest(segment(server, 80, client, 54321, 500, 0, response));
+        assert_eq!(messages.len(), 1);
+        assert!(!messages[0].is_outgoing);
+    }
+
+    #[test]
+    fn reassembles_chunked_request_split_across_segments() {
+        let mut reassembler = Reassembler::new();
+        let client = addr(1);
+        let server = addr(2);
+
+        let headers = b"POST /upload HTTP/1.1\r\nHost: example.com\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let chunked_body = b"5\r\nhello\r\n0\r\n\r\n";
+
+        let early = reassembler.ingest(segment(client, 54321, server, 80, 1000, 0, headers));
+        assert!(early.is_empty());
+
+        let messages = reassembler.ingest(segment(
+            client,
+            54321,
+            server,
+            80,
+            1000 + headers.len() as u32,
+            0,
+            chunked_body,
+        ));
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].data.ends_with(chunked_body));
+    }
+
+    #[test]
+    fn decode_chunked_len_waits_for_the_terminator_chunk() {
+        let partial = b"4\r\nWiki\r\n";
+        assert_eq!(decode_chunked_len(partial), None);
+
+        let complete = b"4\r\nWiki\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked_len(complete), Some(complete.len()));
+    }
+
+    #[test]
+    fn decode_chunked_body_concatenates_chunks_and_drops_terminator() {
+        let body = b"4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        assert_eq!(decode_chunked_body(body), Some(b"Wikipedia".to_vec()));
+    }
+
+    #[test]
+    fn decode_chunked_body_returns_what_arrived_on_truncation() {
+        let truncated = b"4\r\nWiki\r\n5\r\npe";
+        assert_eq!(decode_chunked_body(truncated), Some(b"Wiki".to_vec()));
+    }
+
+    #[test]
+    fn decode_chunked_len_rejects_an_overflowing_chunk_size_instead_of_panicking() {
+        let hostile = b"ffffffffffffffff\r\nx";
+        assert_eq!(decode_chunked_len(hostile), None);
+    }
+
+    #[test]
+    fn decode_chunked_body_rejects_an_overflowing_chunk_size_instead_of_panicking() {
+        let hostile = b"ffffffffffffffff\r\nx";
+        assert_eq!(decode_chunked_body(hostile), Some(Vec::new()));
+    }
+
+    #[test]
+    fn reassembler_does_not_panic_on_a_hostile_content_length() {
+        let mut reassembler = Reassembler::new();
+        let client = addr(1);
+        let server = addr(2);
+
+        let response =
+            b"HTTP/1.1 200 OK\r\nContent-Length: 18446744073709551615\r\n\r\nshort body";
+        let messages = reassembler.ingest(segment(server, 80, client, 54321, 1000, 0, response));
+
+        // The advertised length can never arrive, so the message just never
+        // completes - it must not panic or produce a bogus result.
+        assert!(messages.is_empty());
+    }
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+use log::{debug, info, warn};
+
+use super::interface_detection::{InterfaceDetectionError, RouteInfo};
+
+/// Abstracts over how the OS routing table and interface activity are obtained,
+/// so `InterfaceDetector` can work the same way on every platform instead of
+/// shelling out to a single OS-specific tool.
+pub trait RoutingBackend {
+    /// Return the system's current routing table.
+    fn get_routing_table(&self) -> Result<Vec<RouteInfo>, InterfaceDetectionError>;
+
+    /// Return per-interface byte counters, used to detect which interfaces
+    /// are actually carrying traffic.
+    fn get_interface_activity(&self) -> HashMap<String, u64>;
+}
+
+/// Select the appropriate backend for the current platform at compile time.
+pub fn default_backend() -> Box<dyn RoutingBackend> {
+    #[cfg(target_os = "linux")]
+    {
+        Box::new(LinuxNetlinkBackend)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Box::new(MacosNetstatBackend)
+    }
+}
+
+/// Routing backend that shells out to the BSD/macOS `netstat` tool and parses
+/// its text output. This is the original implementation, kept as-is for
+/// platforms without a netlink-style routing API.
+pub struct MacosNetstatBackend;
+
+impl RoutingBackend for MacosNetstatBackend {
+    fn get_routing_table(&self) -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
+        let output = Command::new("netstat")
+            .args(["-rn", "-f", "inet"])
+            .output()
+            .map_err(|e| InterfaceDetectionError::CommandFailed(format!("netstat failed: {}", e)))?;
+
+        if !output.status.success() {
+            return Err(InterfaceDetectionError::CommandFailed(
+                "netstat command failed".to_string(),
+            ));
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        debug!("Routing table output:\n{}", output_str);
+
+        Self::parse_routing_table(&output_str)
+    }
+
+    fn get_interface_activity(&self) -> HashMap<String, u64> {
+        let mut activity = HashMap::new();
+
+        if let Ok(output) = Command::new("netstat").args(["-i", "-b"]).output() {
+            if output.status.success() {
+                let output_str = String::from_utf8_lossy(&output.stdout);
+
+                for line in output_str.lines() {
+                    if let Some(stats) = Self::parse_interface_stats_line(line) {
+                        activity.insert(stats.0, stats.1);
+                    }
+                }
+            }
+        }
+
+        activity
+    }
+}
+
+impl MacosNetstatBackend {
+    fn parse_routing_table(output: &str) -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
+        let mut routes = Vec::new();
+        let mut parsing_routes = false;
+
+        for line in output.lines() {
+            let line = line.trim();
+
+            // Skip until we reach the routing table section
+            if line.starts_with("Destination") {
+                parsing_routes = true;
+                continue;
+            }
+
+            if !parsing_routes || line.is_empty() {
+                continue;
+            }
+
+            // Parse routing table line: Destination Gateway Flags Interface
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() >= 4 {
+                let route = RouteInfo {
+                    destination: parts[0].to_string(),
+                    gateway: parts[1].to_string(),
+                    flags: parts[2].to_string(),
+                    interface: parts[3].to_string(),
+                };
+                routes.push(route);
+            }
+        }
+
+        if routes.is_empty() {
+            return Err(InterfaceDetectionError::RoutingTableParse);
+        }
+
+        debug!("Parsed {} routes", routes.len());
+        Ok(routes)
+    }
+
+    fn parse_interface_stats_line(line: &str) -> Option<(String, u64)> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() >= 8 {
+            // Interface name is in first column, bytes in column 6 (out) or 7 (in)
+            let interface = parts[0].to_string();
+            if let Ok(bytes_out) = parts[6].parse::<u64>() {
+                return Some((interface, bytes_out));
+            }
+        }
+        None
+    }
+}
+
+/// Routing backend for Linux that talks to the kernel's rtnetlink socket
+/// directly instead of parsing command output, via `rtnetlink`/`netlink-packet-route`.
+pub struct LinuxNetlinkBackend;
+
+impl RoutingBackend for LinuxNetlinkBackend {
+    fn get_routing_table(&self) -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
+        let rt = tokio::runtime::Runtime::new().map_err(|e| {
+            InterfaceDetectionError::CommandFailed(format!("failed to start netlink runtime: {}", e))
+        })?;
+
+        rt.block_on(Self::fetch_routes())
+    }
+
+    fn get_interface_activity(&self) -> HashMap<String, u64> {
+        // Netlink doesn't expose cumulative byte counters through RTM_GETROUTE;
+        // fall back to reading them straight out of sysfs.
+        let mut activity = HashMap::new();
+
+        let Ok(entries) = std::fs::read_dir("/sys/class/net") else {
+            return activity;
+        };
+
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stat_path = entry.path().join("statistics/tx_bytes");
+            if let Ok(contents) = std::fs::read_to_string(&stat_path) {
+                if let Ok(bytes) = contents.trim().parse::<u64>() {
+                    activity.insert(name, bytes);
+                }
+            }
+        }
+
+        activity
+    }
+}
+
+impl LinuxNetlinkBackend {
+    async fn fetch_routes() -> Result<Vec<RouteInfo>, InterfaceDetectionError> {
+        use futures::stream::TryStreamExt;
+        use netlink_packet_route::route::RouteAttribute;
+
+        let (connection, handle, _) = rtnetlink::new_connection().map_err(|e| {
+            InterfaceDetectionError::CommandFailed(format!("failed to open netlink socket: {}", e))
+        })?;
+        tokio::spawn(connection);
+
+        let link_names = Self::fetch_link_names(&handle).await?;
+
+        let mut routes = Vec::new();
+        let mut route_stream = handle.route().get(rtnetlink::IpVersion::V4).execute();
+
+        while let Some(route_msg) = route_stream
+            .try_next()
+            .await
+            .map_err(|e| InterfaceDetectionError::CommandFailed(format!("RTM_GETROUTE failed: {}", e)))?
+        {
+            let prefix_len = route_msg.header.destination_prefix_length;
+
+            let mut destination = None;
+            let mut gateway = String::new();
+            let mut oif = None;
+
+            for attr in route_msg.attributes {
+                match attr {
+                    RouteAttribute::Destination(addr) => destination = Some(addr.to_string()),
+                    RouteAttribute::Gateway(addr) => gateway = addr.to_string(),
+                    RouteAttribute::Oif(index) => oif = Some(index),
+                    _ => {}
+                }
+            }
+
+            let destination = match destination {
+                Some(dst) => format!("{}/{}", dst, prefix_len),
+                None => "default".to_string(),
+            };
+
+            let Some(oif) = oif else { continue };
+            let Some(interface) = link_names.get(&oif) else {
+                warn!("Route referenced unknown interface index {}", oif);
+                continue;
+            };
+
+            routes.push(RouteInfo {
+                destination,
+                gateway: gateway.clone(),
+                interface: interface.clone(),
+                flags: Self::format_flags(&gateway, prefix_len),
+            });
+        }
+
+        if routes.is_empty() {
+            return Err(InterfaceDetectionError::RoutingTableParse);
+        }
+
+        info!("Parsed {} routes via netlink", routes.len());
+        Ok(routes)
+    }
+
+    /// Build a BSD-`netstat`-style flags string (e.g. `UG`, `UGH`) from the
+    /// route fields netlink actually gives us, so the Linux and macOS
+    /// backends report a comparable FLAGS column instead of Linux's always
+    /// coming back empty.
+    fn format_flags(gateway: &str, prefix_len: u8) -> String {
+        let mut flags = String::from("U"); // the route is up
+        if !gateway.is_empty() {
+            flags.push('G'); // routes via a gateway
+        }
+        if prefix_len == 32 {
+            flags.push('H'); // host route (single address, not a subnet)
+        }
+        flags
+    }
+
+    async fn fetch_link_names(
+        handle: &rtnetlink::Handle,
+    ) -> Result<HashMap<u32, String>, InterfaceDetectionError> {
+        use futures::stream::TryStreamExt;
+        use netlink_packet_route::link::LinkAttribute;
+
+        let mut names = HashMap::new();
+        let mut link_stream = handle.link().get().execute();
+
+        while let Some(link_msg) = link_stream
+            .try_next()
+            .await
+            .map_err(|e| InterfaceDetectionError::CommandFailed(format!("RTM_GETLINK failed: {}", e)))?
+        {
+            let index = link_msg.header.index;
+            for attr in link_msg.attributes {
+                if let LinkAttribute::IfName(name) = attr {
+                    names.insert(index, name);
+                    break;
+                }
+            }
+        }
+
+        Ok(names)
+    }
+}
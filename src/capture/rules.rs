@@ -0,0 +1,239 @@
+use std::net::IpAddr;
+
+use crate::http_parser::HttpRequest;
+
+/// Whether a packet/message should be kept or thrown away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+}
+
+/// What a rule matches on. Port/host/CIDR matchers translate cleanly to a BPF
+/// filter and are evaluated kernel-side; the HTTP-level matchers need a parsed
+/// message and are evaluated by `Routine::evaluate_http` after parsing.
+/// `Interface` has no BPF form and is evaluated by neither.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    SourcePort(u16),
+    DestPort(u16),
+    Host(String),
+    Cidr { network: IpAddr, prefix_len: u8 },
+    Interface(String),
+    HttpMethod(String),
+    HttpHost(String),
+}
+
+impl Matcher {
+    /// Whether this matcher can be compiled into the pcap BPF filter string,
+    /// as opposed to needing a parsed HTTP message to evaluate.
+    fn is_bpf_matcher(&self) -> bool {
+        !matches!(self, Matcher::HttpMethod(_) | Matcher::HttpHost(_) | Matcher::Interface(_))
+    }
+
+    /// Render as a standalone BPF expression. Only valid for matchers where
+    /// `is_bpf_matcher()` is true.
+    fn to_bpf(&self) -> Option<String> {
+        match self {
+            Matcher::SourcePort(port) => Some(format!("src port {}", port)),
+            Matcher::DestPort(port) => Some(format!("dst port {}", port)),
+            Matcher::Host(host) => Some(format!("host {}", host)),
+            Matcher::Cidr { network, prefix_len } => Some(format!("net {}/{}", network, prefix_len)),
+            Matcher::Interface(_) => None, // interface is selected at capture-open time, not via BPF
+            Matcher::HttpMethod(_) | Matcher::HttpHost(_) => None,
+        }
+    }
+
+    /// Evaluate an HTTP-level matcher against a parsed message. Non-HTTP
+    /// matchers always match here (port/host/CIDR ones were already enforced
+    /// by BPF).
+    fn matches_http(&self, request: &HttpRequest) -> bool {
+        match self {
+            Matcher::HttpMethod(method) => matches!(
+                request,
+                HttpRequest::Request { method: m, .. } if m.eq_ignore_ascii_case(method)
+            ),
+            Matcher::HttpHost(host) => match request {
+                HttpRequest::Request { headers, .. } => headers
+                    .iter()
+                    .any(|(k, v)| k.eq_ignore_ascii_case("host") && v.eq_ignore_ascii_case(host)),
+                HttpRequest::Response { .. } => false,
+            },
+            _ => true,
+        }
+    }
+}
+
+/// An action to take, together with its verdict. Currently every rule just
+/// yields a verdict directly, but this stays a separate type so future
+/// actions (e.g. logging, sampling) can be added without reshaping `Rule`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Action {
+    pub verdict: Verdict,
+}
+
+impl Action {
+    pub fn accept() -> Self {
+        Action { verdict: Verdict::Accept }
+    }
+
+    pub fn drop() -> Self {
+        Action { verdict: Verdict::Drop }
+    }
+}
+
+/// A single ordered rule: if `matcher` matches, `action` decides the verdict.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub matcher: Matcher,
+    pub action: Action,
+}
+
+impl Rule {
+    pub fn new(matcher: Matcher, action: Action) -> Self {
+        Self { matcher, action }
+    }
+}
+
+/// An ordered set of rules evaluated top-to-bottom, first match wins,
+/// default-accept if nothing matches.
+#[derive(Debug, Clone, Default)]
+pub struct Routine {
+    rules: Vec<Rule>,
+}
+
+impl Routine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Compile every BPF-eligible matcher into a single pcap filter string,
+    /// ANDed with the base `tcp` filter so kernel-side filtering does as much
+    /// of the work as possible. HTTP-level rules are skipped here; they're
+    /// evaluated later in `evaluate_http`.
+    ///
+    /// Rules fold from last to first into one boolean expression, mirroring
+    /// `evaluate_http`'s top-to-bottom first-match-wins semantics: each
+    /// rule's clause overrides whatever the rules after it decided, but only
+    /// for packets it actually matches. So "accept host X" followed by
+    /// "drop net Y" compiles to "host X or not (net Y)" - an OR, since
+    /// matching the first rule alone is enough to keep a packet - not an AND
+    /// of both clauses, which would require matching both to keep anything.
+    pub fn to_bpf_filter(&self) -> String {
+        let bpf_rules: Vec<(String, Verdict)> = self
+            .rules
+            .iter()
+            .filter(|rule| rule.matcher.is_bpf_matcher())
+            .filter_map(|rule| rule.matcher.to_bpf().map(|clause| (clause, rule.action.verdict)))
+            .collect();
+
+        if bpf_rules.is_empty() {
+            return "tcp".to_string();
+        }
+
+        let mut kept = "true".to_string(); // default accept, if nothing else matches
+        for (clause, verdict) in bpf_rules.into_iter().rev() {
+            kept = match verdict {
+                Verdict::Accept => format!("({}) or ({})", clause, kept),
+                Verdict::Drop => format!("not ({}) and ({})", clause, kept),
+            };
+        }
+
+        format!("tcp and ({})", kept)
+    }
+
+    /// Evaluate the HTTP-level rules against a parsed message, top-to-bottom,
+    /// first match wins, default-accept if none match.
+    pub fn evaluate_http(&self, request: &HttpRequest) -> Verdict {
+        for rule in &self.rules {
+            if matches!(rule.matcher, Matcher::HttpMethod(_) | Matcher::HttpHost(_))
+                && rule.matcher.matches_http(request)
+            {
+                return rule.action.verdict;
+            }
+        }
+
+        Verdict::Accept
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn request() -> HttpRequest {
+        HttpRequest::Request {
+            method: "GET".to_string(),
+            url: "http://example.com/".to_string(),
+            headers: HashMap::from([("Host".to_string(), "example.com".to_string())]),
+            body: None,
+        }
+    }
+
+    #[test]
+    fn to_bpf_filter_with_no_rules_is_just_tcp() {
+        let routine = Routine::new(vec![]);
+        assert_eq!(routine.to_bpf_filter(), "tcp");
+    }
+
+    #[test]
+    fn to_bpf_filter_negates_a_lone_drop_rule() {
+        let routine = Routine::new(vec![Rule::new(Matcher::DestPort(80), Action::drop())]);
+        assert_eq!(routine.to_bpf_filter(), "tcp and (not (dst port 80) and (true))");
+    }
+
+    #[test]
+    fn to_bpf_filter_keeps_a_lone_accept_rule_as_a_positive_clause() {
+        let routine = Routine::new(vec![Rule::new(Matcher::SourcePort(443), Action::accept())]);
+        assert_eq!(routine.to_bpf_filter(), "tcp and ((src port 443) or (true))");
+    }
+
+    #[test]
+    fn to_bpf_filter_ors_an_accept_with_a_later_drop_instead_of_anding_them() {
+        // "accept host X, then drop net Y" should keep anything matching X
+        // regardless of Y, not require matching both clauses at once.
+        let routine = Routine::new(vec![
+            Rule::new(Matcher::Host("example.com".to_string()), Action::accept()),
+            Rule::new(Matcher::Cidr { network: "10.0.0.0".parse().unwrap(), prefix_len: 8 }, Action::drop()),
+        ]);
+
+        assert_eq!(
+            routine.to_bpf_filter(),
+            "tcp and ((host example.com) or (not (net 10.0.0.0/8) and (true)))"
+        );
+    }
+
+    #[test]
+    fn to_bpf_filter_skips_http_level_matchers() {
+        let routine = Routine::new(vec![Rule::new(Matcher::HttpHost("example.com".to_string()), Action::drop())]);
+        assert_eq!(routine.to_bpf_filter(), "tcp");
+    }
+
+    #[test]
+    fn evaluate_http_is_default_accept_with_no_matching_rule() {
+        let routine = Routine::new(vec![Rule::new(Matcher::HttpMethod("POST".to_string()), Action::drop())]);
+        assert_eq!(routine.evaluate_http(&request()), Verdict::Accept);
+    }
+
+    #[test]
+    fn evaluate_http_first_match_wins_across_mixed_verdicts() {
+        let routine = Routine::new(vec![
+            Rule::new(Matcher::HttpHost("example.com".to_string()), Action::accept()),
+            Rule::new(Matcher::HttpMethod("GET".to_string()), Action::drop()),
+        ]);
+
+        // The accept rule matches first, so the later drop rule never runs.
+        assert_eq!(routine.evaluate_http(&request()), Verdict::Accept);
+    }
+
+    #[test]
+    fn evaluate_http_falls_through_to_a_later_rule_when_the_first_does_not_match() {
+        let routine = Routine::new(vec![
+            Rule::new(Matcher::HttpHost("other.com".to_string()), Action::accept()),
+            Rule::new(Matcher::HttpMethod("GET".to_string()), Action::drop()),
+        ]);
+
+        assert_eq!(routine.evaluate_http(&request()), Verdict::Drop);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+/// A local process that owns an open socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub name: String,
+    pub pid: u32,
+}
+
+/// Endpoint key used to look up the owning process of a captured flow: the
+/// local address and port of the socket, since that's what the OS's open
+/// socket table is indexed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LocalEndpoint {
+    pub addr: IpAddr,
+    pub port: u16,
+}
+
+/// Resolves captured TCP flows to the local process that owns them, so
+/// traced HTTP requests can be labeled by originating app.
+///
+/// Building the open-socket table is expensive (it shells out or walks
+/// `/proc`), so the table is cached and only refreshed lazily.
+pub struct SocketAttributor {
+    table: HashMap<LocalEndpoint, ProcessInfo>,
+    last_refresh: Option<Instant>,
+    refresh_interval: Duration,
+}
+
+impl Default for SocketAttributor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SocketAttributor {
+    pub fn new() -> Self {
+        Self {
+            table: HashMap::new(),
+            last_refresh: None,
+            refresh_interval: Duration::from_secs(2),
+        }
+    }
+
+    /// Resolve the process owning the local side of a captured 5-tuple,
+    /// refreshing the cached socket table first if it's gone stale.
+    pub fn lookup(&mut self, local_addr: IpAddr, local_port: u16) -> Option<ProcessInfo> {
+        self.refresh_if_stale();
+        self.table
+            .get(&LocalEndpoint {
+                addr: local_addr,
+                port: local_port,
+            })
+            .cloned()
+    }
+
+    fn refresh_if_stale(&mut self) {
+        let needs_refresh = match self.last_refresh {
+            Some(last) => last.elapsed() >= self.refresh_interval,
+            None => true,
+        };
+
+        if needs_refresh {
+            self.table = get_open_sockets();
+            self.last_refresh = Some(Instant::now());
+        }
+    }
+}
+
+/// Build a snapshot of `(local_addr, local_port) -> ProcessInfo` for every
+/// open TCP socket on the system.
+pub fn get_open_sockets() -> HashMap<LocalEndpoint, ProcessInfo> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::get_open_sockets()
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        macos::get_open_sockets()
+    }
+}
+
+/// Returns `root_pid` together with every PID descended from it at the
+/// time of the call (its children, grandchildren, etc.), so captured flows
+/// can be scoped to a launched command's own process tree.
+pub fn descendant_pids(root_pid: u32) -> HashSet<u32> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::descendant_pids(root_pid)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        macos::descendant_pids(root_pid)
+    }
+}
+
+/// Walk (depth-first) out from `root_pid` over a pid -> children map to
+/// collect the full set of its descendants, including itself.
+fn collect_descendants(root_pid: u32, children: &HashMap<u32, Vec<u32>>) -> HashSet<u32> {
+    let mut result = HashSet::new();
+    let mut queue = vec![root_pid];
+
+    while let Some(pid) = queue.pop() {
+        if result.insert(pid) {
+            if let Some(kids) = children.get(&pid) {
+                queue.extend(kids);
+            }
+        }
+    }
+
+    result
+}
+
+/// Restricts captured flows to those owned by a launched command's own
+/// process tree, so `--command` traces don't pick up unrelated traffic
+/// sharing the interface.
+///
+/// Like [`SocketAttributor`], the descendant-pid set is expensive to
+/// rebuild (it walks `/proc` or shells out), so it's cached and refreshed
+/// lazily rather than recomputed per packet.
+pub struct ProcessScope {
+    attributor: SocketAttributor,
+    root_pid: u32,
+    descendants: HashSet<u32>,
+    last_refresh: Option<Instant>,
+    refresh_interval: Duration,
+}
+
+impl ProcessScope {
+    pub fn new(root_pid: u32) -> Self {
+        Self {
+            attributor: SocketAttributor::new(),
+            root_pid,
+            descendants: HashSet::from([root_pid]),
+            last_refresh: None,
+            refresh_interval: Duration::from_secs(2),
+        }
+    }
+
+    /// Whether either side of a captured flow's local endpoints is owned by
+    /// a process in this command's tree.
+    pub fn owns(&mut self, src: (IpAddr, u16), dst: (IpAddr, u16)) -> bool {
+        self.refresh_if_stale();
+        self.endpoint_in_scope(src) || self.endpoint_in_scope(dst)
+    }
+
+    fn endpoint_in_scope(&mut self, endpoint: (IpAddr, u16)) -> bool {
+        match self.attributor.lookup(endpoint.0, endpoint.1) {
+            Some(process) => self.descendants.contains(&process.pid),
+            None => false,
+        }
+    }
+
+    fn refresh_if_stale(&mut self) {
+        let needs_refresh = match self.last_refresh {
+            Some(last) => last.elapsed() >= self.refresh_interval,
+            None => true,
+        };
+
+        if needs_refresh {
+            self.descendants = descendant_pids(self.root_pid);
+            self.last_refresh = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod macos {
+    use super::*;
+
+    /// Parse `lsof -nP -i4 -i6` into a map of local endpoint to owning process.
+    pub fn get_open_sockets() -> HashMap<LocalEndpoint, ProcessInfo> {
+        let mut table = HashMap::new();
+
+        let output = match Command::new("lsof").args(["-nP", "-i4", "-i6"]).output() {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Failed to run lsof: {}", e);
+                return table;
+            }
+        };
+
+        if !output.status.success() {
+            warn!("lsof exited with a non-zero status");
+            return table;
+        }
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        for line in output_str.lines().skip(1) {
+            if let Some((endpoint, info)) = parse_lsof_line(line) {
+                table.insert(endpoint, info);
+            }
+        }
+
+        table
+    }
+
+    /// Parse a single `lsof` output line, e.g.:
+    /// `curl      1234 user    5u  IPv4 0x...      0t0  TCP 127.0.0.1:54321->93.184.216.34:80 (ESTABLISHED)`
+    fn parse_lsof_line(line: &str) -> Option<(LocalEndpoint, ProcessInfo)> {
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        if columns.len() < 9 {
+            return None;
+        }
+
+        let name = columns[0].to_string();
+        let pid = columns[1].parse::<u32>().ok()?;
+        let name_field = columns[8];
+
+        let local_part = name_field.split("->").next().unwrap_or(name_field);
+        let (addr_str, port_str) = local_part.rsplit_once(':')?;
+        let addr_str = addr_str.trim_start_matches('[').trim_end_matches(']');
+
+        let addr: IpAddr = addr_str.parse().ok()?;
+        let port: u16 = port_str.parse().ok()?;
+
+        Some((LocalEndpoint { addr, port }, ProcessInfo { name, pid }))
+    }
+
+    /// Walk `ps -axo pid=,ppid=` to build a pid -> ppid map, then walk out
+    /// from `root_pid` to collect its descendants.
+    pub fn descendant_pids(root_pid: u32) -> HashSet<u32> {
+        let output = match Command::new("ps").args(["-axo", "pid=,ppid="]).output() {
+            Ok(output) => output,
+            Err(e) => {
+                warn!("Failed to run ps: {}", e);
+                return HashSet::from([root_pid]);
+            }
+        };
+
+        let output_str = String::from_utf8_lossy(&output.stdout);
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for line in output_str.lines() {
+            let mut columns = line.split_whitespace();
+            let Some(pid) = columns.next().and_then(|s| s.parse::<u32>().ok()) else {
+                continue;
+            };
+            let Some(ppid) = columns.next().and_then(|s| s.parse::<u32>().ok()) else {
+                continue;
+            };
+            children.entry(ppid).or_default().push(pid);
+        }
+
+        collect_descendants(root_pid, &children)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::*;
+    use std::fs;
+
+    /// Read `/proc/net/tcp` and `/proc/net/tcp6` for the inode -> (addr, port)
+    /// mapping, then join against `/proc/<pid>/fd` symlinks to find which
+    /// process owns each inode.
+    pub fn get_open_sockets() -> HashMap<LocalEndpoint, ProcessInfo> {
+        let mut sockets_by_inode = HashMap::new();
+        sockets_by_inode.extend(parse_proc_net_tcp("/proc/net/tcp", false));
+        sockets_by_inode.extend(parse_proc_net_tcp("/proc/net/tcp6", true));
+
+        let inode_to_process = map_inodes_to_processes();
+
+        let mut table = HashMap::new();
+        for (inode, endpoint) in sockets_by_inode {
+            if let Some(process) = inode_to_process.get(&inode) {
+                table.insert(endpoint, process.clone());
+            }
+        }
+
+        table
+    }
+
+    /// Parse `/proc/net/tcp{,6}`, returning a map of socket inode to local endpoint.
+    fn parse_proc_net_tcp(path: &str, is_v6: bool) -> HashMap<u64, LocalEndpoint> {
+        let mut sockets = HashMap::new();
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                debug!("Failed to read {}: {}", path, e);
+                return sockets;
+            }
+        };
+
+        for line in contents.lines().skip(1) {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+            if columns.len() < 10 {
+                continue;
+            }
+
+            let Some((local_addr, local_port)) = parse_hex_addr_port(columns[1], is_v6) else {
+                continue;
+            };
+            let Ok(inode) = columns[9].parse::<u64>() else {
+                continue;
+            };
+
+            sockets.insert(
+                inode,
+                LocalEndpoint {
+                    addr: local_addr,
+                    port: local_port,
+                },
+            );
+        }
+
+        sockets
+    }
+
+    /// Parse the `HEXADDR:HEXPORT` fields used by `/proc/net/tcp{,6}`. Addresses
+    /// are stored little-endian per 32-bit word.
+    fn parse_hex_addr_port(field: &str, is_v6: bool) -> Option<(IpAddr, u16)> {
+        let (addr_hex, port_hex) = field.split_once(':')?;
+        let port = u16::from_str_radix(port_hex, 16).ok()?;
+
+        let addr = if is_v6 {
+            if addr_hex.len() != 32 {
+                return None;
+            }
+            let mut bytes = [0u8; 16];
+            for word in 0..4 {
+                let word_hex = &addr_hex[word * 8..word * 8 + 8];
+                let word_val = u32::from_str_radix(word_hex, 16).ok()?;
+                bytes[word * 4..word * 4 + 4].copy_from_slice(&word_val.to_le_bytes());
+            }
+            IpAddr::from(bytes)
+        } else {
+            let word_val = u32::from_str_radix(addr_hex, 16).ok()?;
+            IpAddr::from(word_val.to_le_bytes())
+        };
+
+        Some((addr, port))
+    }
+
+    /// Walk `/proc/<pid>/fd` symlinks for every process to build an inode to
+    /// `ProcessInfo` map (symlinks to sockets look like `socket:[12345]`).
+    fn map_inodes_to_processes() -> HashMap<u64, ProcessInfo> {
+        let mut map = HashMap::new();
+
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return map;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+
+            let name = match fs::read_to_string(format!("/proc/{}/comm", pid)) {
+                Ok(name) => name.trim().to_string(),
+                Err(_) => continue,
+            };
+
+            let fd_dir = format!("/proc/{}/fd", pid);
+            let Ok(fds) = fs::read_dir(&fd_dir) else {
+                continue;
+            };
+
+            for fd in fds.flatten() {
+                let Ok(link) = fs::read_link(fd.path()) else {
+                    continue;
+                };
+                let Some(inode) = parse_socket_inode(&link.to_string_lossy()) else {
+                    continue;
+                };
+
+                map.entry(inode).or_insert_with(|| ProcessInfo {
+                    name: name.clone(),
+                    pid,
+                });
+            }
+        }
+
+        map
+    }
+
+    fn parse_socket_inode(link: &str) -> Option<u64> {
+        let inner = link.strip_prefix("socket:[")?.strip_suffix(']')?;
+        inner.parse::<u64>().ok()
+    }
+
+    /// Read `/proc/<pid>/stat` for every process to build a pid -> ppid map,
+    /// then walk out from `root_pid` to collect its descendants.
+    pub fn descendant_pids(root_pid: u32) -> HashSet<u32> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+
+        let Ok(entries) = fs::read_dir("/proc") else {
+            return HashSet::from([root_pid]);
+        };
+
+        for entry in entries.flatten() {
+            let Ok(pid) = entry.file_name().to_string_lossy().parse::<u32>() else {
+                continue;
+            };
+            if let Some(ppid) = read_ppid(pid) {
+                children.entry(ppid).or_default().push(pid);
+            }
+        }
+
+        collect_descendants(root_pid, &children)
+    }
+
+    /// Parse the parent PID out of `/proc/<pid>/stat`. The `comm` field can
+    /// itself contain spaces and parens, so skip past the last closing paren
+    /// before splitting the remaining whitespace-delimited fields (state, ppid, ...).
+    fn read_ppid(pid: u32) -> Option<u32> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+        let after_comm = stat.rsplit_once(')')?.1;
+        after_comm.split_whitespace().nth(1)?.parse::<u32>().ok()
+    }
+}
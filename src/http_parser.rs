@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use crate::capture::reassembly;
+
 #[derive(Debug)]
 pub enum HttpRequest {
     Request {
@@ -15,16 +17,22 @@ pub enum HttpRequest {
     },
 }
 
+/// Parse a reassembled message as a request or a response, preferring
+/// whichever `is_outgoing` (the reassembler's best guess at direction)
+/// suggests, but falling back to the other parser if that guess turns out
+/// to be wrong - e.g. for a flow whose handshake `SYN` wasn't observed, or
+/// any other case where direction was misjudged - so a message isn't
+/// silently dropped just because it was fed to the wrong parser first.
 pub fn parse_http_data(data: &str, is_outgoing: bool) -> Option<HttpRequest> {
     let lines: Vec<&str> = data.lines().collect();
     if lines.is_empty() {
         return None;
     }
-    
+
     if is_outgoing {
-        parse_http_request(data)
+        parse_http_request(data).or_else(|| parse_http_response(data))
     } else {
-        parse_http_response(data)
+        parse_http_response(data).or_else(|| parse_http_request(data))
     }
 }
 
@@ -34,10 +42,10 @@ fn parse_http_request(data: &str) -> Option<HttpRequest> {
     headers.headers = &mut header_buf;
     
     match headers.parse(data.as_bytes()) {
-        Ok(httparse::Status::Complete(_)) => {
+        Ok(httparse::Status::Complete(body_start)) => {
             let method = headers.method?.to_string();
             let path = headers.path?.to_string();
-            
+
             let mut header_map = HashMap::new();
             for header in headers.headers.iter() {
                 if !header.name.is_empty() {
@@ -45,24 +53,26 @@ fn parse_http_request(data: &str) -> Option<HttpRequest> {
                     header_map.insert(header.name.to_string(), value.to_string());
                 }
             }
-            
+
             // Try to construct full URL
             let host = header_map.get("Host")
                 .or_else(|| header_map.get("host"))
                 .map(|h| h.as_str())
                 .unwrap_or("unknown");
-                
+
             let url = if path.starts_with("http") {
                 path
             } else {
                 format!("http://{}{}", host, path)
             };
-            
+
+            let body = extract_body(&header_map, data.as_bytes(), body_start);
+
             Some(HttpRequest::Request {
                 method,
                 url,
                 headers: header_map,
-                body: None, // TODO: Parse body if needed
+                body,
             })
         }
         _ => None,
@@ -75,12 +85,12 @@ fn parse_http_response(data: &str) -> Option<HttpRequest> {
     response.headers = &mut header_buf;
     
     match response.parse(data.as_bytes()) {
-        Ok(httparse::Status::Complete(_)) => {
-            let status = format!("{} {}", 
-                response.code?, 
+        Ok(httparse::Status::Complete(body_start)) => {
+            let status = format!("{} {}",
+                response.code?,
                 response.reason.unwrap_or("Unknown")
             );
-            
+
             let mut header_map = HashMap::new();
             for header in response.headers.iter() {
                 if !header.name.is_empty() {
@@ -88,13 +98,42 @@ fn parse_http_response(data: &str) -> Option<HttpRequest> {
                     header_map.insert(header.name.to_string(), value.to_string());
                 }
             }
-            
+
+            let body = extract_body(&header_map, data.as_bytes(), body_start);
+
             Some(HttpRequest::Response {
                 status,
                 headers: header_map,
-                body: None, // TODO: Parse body if needed
+                body,
             })
         }
         _ => None,
     }
 }
+
+/// Pull the body out of `data` starting at `body_start`, decoding it first if
+/// `Transfer-Encoding: chunked` was used. Returns `None` if there's no body
+/// bytes available (e.g. a headers-only message, or one still in flight).
+fn extract_body(headers: &HashMap<String, String>, data: &[u8], body_start: usize) -> Option<String> {
+    if body_start >= data.len() {
+        return None;
+    }
+
+    let raw_body = &data[body_start..];
+
+    let is_chunked = headers
+        .iter()
+        .any(|(name, value)| name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked"));
+
+    let body_bytes = if is_chunked {
+        reassembly::decode_chunked_body(raw_body)?
+    } else {
+        raw_body.to_vec()
+    };
+
+    if body_bytes.is_empty() {
+        return None;
+    }
+
+    Some(String::from_utf8_lossy(&body_bytes).to_string())
+}
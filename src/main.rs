@@ -1,15 +1,20 @@
 use clap::Parser;
 use std::process::{Command as StdCommand, Stdio};
 use std::time::{Duration, Instant};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::sync::{mpsc, Arc, atomic::{AtomicBool, Ordering}};
 use std::thread;
 use log::{info, error, warn};
 
 mod http_parser;
 mod capture;
+mod output;
+mod reporting;
+mod resolve;
 
-use http_parser::HttpRequest;
-use capture::{PcapCapture, CaptureError, InterfaceDetector};
+use capture::{PcapCapture, PcapOfflineCapture, CaptureError, FlowKey, InterfaceDetector, NetworkInterface, ProcessScope, Reassembler, Routine, SocketAttributor, TcpSegment, Verdict};
+use output::{OutputFormat, OutputSink};
+use reporting::ReportFormat;
+use resolve::Resolver;
 
 #[derive(Parser)]
 #[command(version, about = "HTTP traffic tracer using packet capture")]
@@ -17,22 +22,67 @@ struct Args {
     /// Command to execute and trace (optional - if not provided, captures all HTTP traffic)
     #[arg(short, long, value_name = "COMMAND")]
     command: Option<String>,
-    
+
     /// Network interface to capture on (auto-detect if not specified)
     #[arg(short, long, value_name = "INTERFACE")]
     interface: Option<String>,
-    
+
     /// Duration to capture packets (in seconds, default: indefinite)
     #[arg(short, long, value_name = "SECONDS")]
     duration: Option<u64>,
-    
+
     /// List available network interfaces and exit
     #[arg(short, long)]
     list: bool,
-    
+
+    /// List the system routing table and exit
+    #[arg(long = "list-routes")]
+    list_routes: bool,
+
+    /// Output format for --list/--list-routes
+    #[arg(long, value_name = "FORMAT", default_value = "table")]
+    format: ReportFormat,
+
     /// Capture on all active interfaces simultaneously
     #[arg(short = 'A', long)]
     all_interfaces: bool,
+
+    /// Read packets from a saved .pcap file instead of a live interface
+    #[arg(long = "read", value_name = "FILE")]
+    read: Option<String>,
+
+    /// Write every captured packet to a .pcap file for later replay with --read
+    #[arg(long = "write", value_name = "FILE")]
+    write: Option<String>,
+
+    /// Drop requests to this HTTP host (may be given multiple times)
+    #[arg(long = "drop-host", value_name = "HOST")]
+    drop_hosts: Vec<String>,
+
+    /// Drop requests using this HTTP method (may be given multiple times)
+    #[arg(long = "drop-method", value_name = "METHOD")]
+    drop_methods: Vec<String>,
+
+    /// Output format for captured HTTP transactions: text (default), json
+    /// (newline-delimited JSON to stdout), or har (HAR 1.2 file, see --output-file)
+    #[arg(long = "output", value_name = "FORMAT", default_value = "text")]
+    output: OutputFormat,
+
+    /// File to write the HAR log to; required when --output har is used
+    #[arg(long = "output-file", value_name = "FILE")]
+    output_file: Option<String>,
+
+    /// Resolve each connection's remote IP to a hostname via reverse DNS and
+    /// annotate output with it (default: off, to keep the capture hot path
+    /// lookup-free)
+    #[arg(long)]
+    resolve: bool,
+
+    /// Look up the local process (name + PID) owning each captured flow and
+    /// annotate output with it (default: off; building the open-socket
+    /// table isn't free, see SocketAttributor)
+    #[arg(long)]
+    attribute_process: bool,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -43,15 +93,26 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let args = Args::parse();
 
-    // Handle list command
+    // Handle list commands
     if args.list {
-        list_interfaces()?;
+        list_interfaces(args.format)?;
+        return Ok(());
+    }
+    if args.list_routes {
+        list_routes(args.format)?;
         return Ok(());
     }
 
+    if args.output == OutputFormat::Har && args.output_file.is_none() {
+        return Err("--output har requires --output-file <FILE>".into());
+    }
+
     println!("🔍 Starting httptrace");
-    
-    if let Some(ref cmd) = args.command {
+
+    if let Some(ref path) = args.read {
+        println!("📂 Replaying capture from: {}", path);
+        replay_file(path, &args)?;
+    } else if let Some(ref cmd) = args.command {
         println!("📡 Launching command: {}", cmd);
         capture_with_command(&args)?;
     } else {
@@ -62,85 +123,91 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn list_interfaces() -> Result<(), Box<dyn std::error::Error>> {
-    println!("Available network interfaces:");
-    let devices = PcapCapture::list_devices()?;
-    
-    // Get the default interface for highlighting
-    let default_interface = InterfaceDetector::get_default_interface().ok();
-    let active_interfaces = InterfaceDetector::get_active_interfaces().unwrap_or_default();
-    
-    for device in devices {
-        let mut tags = Vec::new();
-        
-        if Some(&device.name) == default_interface.as_ref() {
-            tags.push("default route");
-        }
-        if active_interfaces.contains(&device.name) {
-            tags.push("active");
-        }
-        
-        let tag_str = if tags.is_empty() {
-            String::new()
-        } else {
-            format!(" [{}]", tags.join(", "))
-        };
-        
-        println!("  {}{} - {}", 
-                device.name,
-                tag_str,
-                device.desc.as_deref().unwrap_or("No description"));
-    }
-    
-    // Show interface statistics
-    println!("\nInterface activity (bytes transmitted):");
-    let activity = InterfaceDetector::get_interface_activity();
-    for (interface, bytes) in activity {
-        if bytes > 0 {
-            println!("  {}: {} bytes", interface, bytes);
+/// Offline mode: run the saved `.pcap` file through the exact same
+/// reassembly/parse/filter pipeline a live capture uses.
+fn replay_file(path: &str, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut capture = PcapOfflineCapture::open(path, routine_from_args(args))?;
+    let routine = capture.routine().clone();
+    let linktype = capture.linktype();
+    let mut reassembler = Reassembler::new();
+    let mut sink = OutputSink::new(args.output, args.output_file.clone());
+    let resolver = resolver_from_args(args);
+    let mut attributor = attributor_from_args(args);
+    let mut packet_count = 0;
+
+    capture.start_capture(|packet_data| {
+        process_packet(packet_data, linktype, &routine, &mut reassembler, &mut sink, None, path, resolver.as_ref(), attributor.as_mut());
+        packet_count += 1;
+        true
+    })?;
+
+    sink.finish()?;
+    println!("✅ Replay completed. Processed {} packets", packet_count);
+    Ok(())
+}
+
+fn list_interfaces(format: ReportFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let interfaces: Vec<NetworkInterface> = NetworkInterface::detect_all();
+    reporting::print_interfaces(&interfaces, format);
+
+    if format == ReportFormat::Table {
+        println!("\nInterface activity (bytes transmitted):");
+        let activity = InterfaceDetector::get_interface_activity();
+        for (interface, bytes) in activity {
+            if bytes > 0 {
+                println!("  {}: {} bytes", interface, bytes);
+            }
         }
     }
-    
+
+    Ok(())
+}
+
+fn list_routes(format: ReportFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let routes = InterfaceDetector::get_routing_table()?;
+    reporting::print_routes(&routes, format);
     Ok(())
 }
 
 fn capture_with_command(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
     let command = args.command.as_ref().unwrap();
-    
-    // Start packet capture in background
-    let mut capture = PcapCapture::new(args.interface.as_deref())?;
-    println!("🔍 Starting capture on interface: {}", capture.interface_name());
-    
+
     // Set up signal handling for graceful shutdown
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
-    
+
     ctrlc::set_handler(move || {
         println!("\n🛑 Received interrupt signal, stopping capture...");
         r.store(false, Ordering::SeqCst);
     })?;
-    
+
     // Launch the command
     let mut child = launch_command(command)?;
-    
-    // Start packet capture
+    let mut scope = Some(ProcessScope::new(child.id()));
+
+    let (tx, rx) = mpsc::channel();
+    let (capture_handles, routine) = spawn_capture_threads(args, running.clone(), tx)?;
+    let output_format = args.output;
+    let output_file = args.output_file.clone();
+
+    let resolve = args.resolve;
+    let attribute_process = args.attribute_process;
     let capture_running = running.clone();
-    let capture_handle = thread::spawn(move || {
-        let result = capture.start_capture(|packet_data| {
-            if !capture_running.load(Ordering::SeqCst) {
-                return false; // Stop capture
-            }
-            
-            // Process the packet for HTTP content
-            process_packet(packet_data);
-            true // Continue capture
-        });
-        
-        if let Err(e) = result {
-            error!("Capture error: {}", e);
+    let consumer_handle = thread::spawn(move || {
+        let mut reassembler = Reassembler::new();
+        let mut sink = OutputSink::new(output_format, output_file);
+        let resolver = if resolve { Some(Resolver::spawn()) } else { None };
+        let mut attributor = if attribute_process { Some(SocketAttributor::new()) } else { None };
+
+        // Process the packet for HTTP content, scoped to the launched
+        // command's own sockets, until every capture thread has stopped.
+        consume_captured_packets(&rx, &routine, &mut reassembler, &mut sink, scope.as_mut(), resolver.as_ref(), attributor.as_mut(), &capture_running);
+
+        if let Err(e) = sink.finish() {
+            error!("Failed to write output: {}", e);
         }
     });
-    
+
     // Wait for command to finish or timeout
     if let Some(duration) = args.duration {
         let start = Instant::now();
@@ -154,61 +221,79 @@ fn capture_with_command(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
         // Wait for command to finish
         child.wait()?;
     }
-    
+
     // Stop capture
     running.store(false, Ordering::SeqCst);
-    
+
     // Clean up
-    if capture_handle.join().is_err() {
-        warn!("Capture thread did not shut down cleanly");
+    for handle in capture_handles {
+        if handle.join().is_err() {
+            warn!("Capture thread did not shut down cleanly");
+        }
     }
-    
+    if consumer_handle.join().is_err() {
+        warn!("Output thread did not shut down cleanly");
+    }
+
     println!("✅ Capture completed");
     Ok(())
 }
 
 fn capture_continuously(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
-    let mut capture = PcapCapture::new(args.interface.as_deref())?;
-    println!("🔍 Starting capture on interface: {}", capture.interface_name());
-    
-    if let Some(duration) = args.duration {
-        println!("⏰ Capture duration: {} seconds", duration);
-    } else {
-        println!("⏰ Press Ctrl+C to stop capture");
-    }
-    
     // Set up signal handling for graceful shutdown
     let running = Arc::new(AtomicBool::new(true));
     let r = running.clone();
-    
+
     ctrlc::set_handler(move || {
         println!("\n🛑 Received interrupt signal, stopping capture...");
         r.store(false, Ordering::SeqCst);
     })?;
-    
+
+    let (tx, rx) = mpsc::channel();
+    let (capture_handles, routine) = spawn_capture_threads(args, running.clone(), tx)?;
+    let resolver = resolver_from_args(args);
+    let mut attributor = attributor_from_args(args);
+
+    if let Some(duration) = args.duration {
+        println!("⏰ Capture duration: {} seconds", duration);
+    } else {
+        println!("⏰ Press Ctrl+C to stop capture");
+    }
+
     let start_time = Instant::now();
+    let mut reassembler = Reassembler::new();
+    let mut sink = OutputSink::new(args.output, args.output_file.clone());
     let mut packet_count = 0;
-    
-    capture.start_capture(|packet_data| {
+
+    loop {
         if !running.load(Ordering::SeqCst) {
-            return false; // Stop capture
+            break;
         }
-        
-        // Check duration limit
         if let Some(duration) = args.duration {
             if start_time.elapsed() >= Duration::from_secs(duration) {
                 println!("⏰ Duration limit reached, stopping capture");
-                return false;
+                running.store(false, Ordering::SeqCst);
+                break;
             }
         }
-        
-        // Process the packet for HTTP content
-        process_packet(packet_data);
-        packet_count += 1;
-        
-        true // Continue capture
-    })?;
-    
+
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(captured) => {
+                process_packet(&captured.data, captured.linktype, &routine, &mut reassembler, &mut sink, None, &captured.interface, resolver.as_ref(), attributor.as_mut());
+                packet_count += 1;
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    for handle in capture_handles {
+        if handle.join().is_err() {
+            warn!("Capture thread did not shut down cleanly");
+        }
+    }
+
+    sink.finish()?;
     println!("✅ Capture completed. Processed {} packets", packet_count);
     Ok(())
 }
@@ -230,66 +315,226 @@ fn launch_command(command: &str) -> Result<std::process::Child, Box<dyn std::err
 
     let child = cmd.spawn()?;
     info!("Launched command with PID: {}", child.id());
-    
+
     Ok(child)
 }
 
-fn process_packet(packet_data: &[u8]) {
-    // For now, we'll implement basic packet processing
-    // This will be enhanced when we add the packet parsing logic
-    
-    // Try to extract HTTP data from the packet
-    if let Some(http_data) = extract_http_from_packet(packet_data) {
-        if let Some(request) = http_parser::parse_http_data(&http_data, true) {
-            print_http_request(&request);
-        }
+/// Build the capture routine from the CLI flags: `--drop-host` and
+/// `--drop-method` denylists, evaluated top-to-bottom, default-accept if
+/// nothing was configured or nothing matched.
+fn routine_from_args(args: &Args) -> Routine {
+    use capture::{Action, Matcher, Rule};
+
+    let mut rules = Vec::new();
+
+    for host in &args.drop_hosts {
+        rules.push(Rule::new(Matcher::HttpHost(host.clone()), Action::drop()));
     }
+    for method in &args.drop_methods {
+        rules.push(Rule::new(Matcher::HttpMethod(method.clone()), Action::drop()));
+    }
+
+    Routine::new(rules)
+}
+
+/// Spawn a background reverse-DNS resolver when `--resolve` is set, or
+/// `None` to keep the capture hot path lookup-free (the default).
+fn resolver_from_args(args: &Args) -> Option<Resolver> {
+    args.resolve.then(Resolver::spawn)
 }
 
-fn extract_http_from_packet(packet_data: &[u8]) -> Option<String> {
-    // Basic implementation - look for HTTP patterns in the packet
-    // This is a simplified version and will need to be enhanced with proper TCP/IP parsing
-    
-    if packet_data.len() < 20 {
-        return None; // Too small to contain meaningful data
+/// Build a `SocketAttributor` when `--attribute-process` is set, or `None`
+/// to keep the capture hot path free of `/proc` walks/`lsof` shell-outs (the
+/// default).
+fn attributor_from_args(args: &Args) -> Option<SocketAttributor> {
+    args.attribute_process.then(SocketAttributor::new)
+}
+
+/// One raw packet handed from a capture thread to the single consumer that
+/// reassembles and prints it, tagged with the interface it came in on.
+struct CapturedPacket {
+    interface: String,
+    linktype: pcap::Linktype,
+    data: Vec<u8>,
+}
+
+/// Open one `PcapCapture` per interface to monitor - just `args.interface`
+/// (or the auto-detected best device) normally, or every interface
+/// `InterfaceDetector::get_active_interfaces` reports when `--all-interfaces`
+/// is set - and spawn a dedicated thread per device that forwards its raw
+/// packets to `tx`. Every thread stops as soon as `running` flips to false,
+/// so a single Ctrl+C handler and shutdown flag covers them all.
+fn spawn_capture_threads(
+    args: &Args,
+    running: Arc<AtomicBool>,
+    tx: mpsc::Sender<CapturedPacket>,
+) -> Result<(Vec<thread::JoinHandle<()>>, Routine), Box<dyn std::error::Error>> {
+    let routine = routine_from_args(args);
+
+    if !args.all_interfaces {
+        let mut capture = PcapCapture::with_routine(args.interface.as_deref(), routine.clone())?;
+        println!("🔍 Starting capture on interface: {}", capture.interface_name());
+        if let Some(ref path) = args.write {
+            capture.write_to_file(path)?;
+            println!("💾 Writing captured packets to: {}", path);
+        }
+        return Ok((vec![spawn_capture_thread(capture, running, tx)], routine));
+    }
+
+    if args.write.is_some() {
+        warn!("--write is not supported with --all-interfaces; ignoring it");
     }
-    
-    // Convert to string and look for HTTP patterns
-    if let Ok(data_str) = std::str::from_utf8(packet_data) {
-        if data_str.contains("HTTP/") || 
-           data_str.starts_with("GET ") ||
-           data_str.starts_with("POST ") ||
-           data_str.starts_with("PUT ") ||
-           data_str.starts_with("DELETE ") {
-            return Some(data_str.to_string());
+
+    let interface_names = InterfaceDetector::get_active_interfaces()?;
+    if interface_names.is_empty() {
+        return Err("No active interfaces found for --all-interfaces".into());
+    }
+
+    let mut handles = Vec::new();
+    for name in interface_names {
+        match PcapCapture::with_routine(Some(&name), routine.clone()) {
+            Ok(capture) => {
+                println!("🔍 Starting capture on interface: {}", capture.interface_name());
+                handles.push(spawn_capture_thread(capture, running.clone(), tx.clone()));
+            }
+            Err(e) => warn!("Skipping interface {}: {}", name, e),
         }
     }
-    
-    None
+
+    if handles.is_empty() {
+        return Err("Failed to open any interface for --all-interfaces".into());
+    }
+
+    Ok((handles, routine))
+}
+
+/// Run one `PcapCapture` to completion on its own thread, forwarding every
+/// packet it reads to `tx` until `running` goes false or the capture itself
+/// errors out.
+fn spawn_capture_thread(
+    mut capture: PcapCapture,
+    running: Arc<AtomicBool>,
+    tx: mpsc::Sender<CapturedPacket>,
+) -> thread::JoinHandle<()> {
+    let interface = capture.interface_name().to_string();
+    let linktype = capture.linktype();
+
+    thread::spawn(move || {
+        let result = capture.start_capture(|packet_data| {
+            if !running.load(Ordering::SeqCst) {
+                return false; // Stop capture
+            }
+
+            let captured = CapturedPacket { interface: interface.clone(), linktype, data: packet_data.to_vec() };
+            tx.send(captured).is_ok()
+        });
+
+        if let Err(e) = result {
+            error!("Capture error on {}: {}", interface, e);
+        }
+    })
 }
 
-fn print_http_request(request: &HttpRequest) {
-    match request {
-        HttpRequest::Request {
-            method,
-            url,
-            headers,
-            ..
-        } => {
-            println!("🚀 {} {}", method, url);
-            for (key, value) in headers {
-                println!("   {}: {}", key, value);
+/// Drain captured packets off `rx`, running each through `process_packet`,
+/// until every capture thread has stopped sending (channel disconnected),
+/// or until `running` has gone false and no packet has arrived for 200ms. Used by `--command` tracing, where the
+/// capture loop's lifetime is driven by the traced process rather than a
+/// `--duration` deadline.
+fn consume_captured_packets(
+    rx: &mpsc::Receiver<CapturedPacket>,
+    routine: &Routine,
+    reassembler: &mut Reassembler,
+    sink: &mut OutputSink,
+    mut scope: Option<&mut ProcessScope>,
+    resolver: Option<&Resolver>,
+    mut attributor: Option<&mut SocketAttributor>,
+    running: &Arc<AtomicBool>,
+) {
+    loop {
+        match rx.recv_timeout(Duration::from_millis(200)) {
+            Ok(captured) => {
+                process_packet(&captured.data, captured.linktype, routine, reassembler, sink, scope.as_deref_mut(), &captured.interface, resolver, attributor.as_deref_mut());
             }
-            println!();
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if !running.load(Ordering::SeqCst) {
+                    break;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/// Feed one captured packet into the stream reassembler and hand every HTTP
+/// message that becomes complete as a result off to the routine/printer.
+/// Packets are decoded through `capture::packet`, which strips the real
+/// link/IP/TCP headers instead of treating the raw frame as HTTP text.
+///
+/// When `scope` is set (i.e. we're tracing a launched `--command`), packets
+/// not owned by that process tree are dropped before they ever reach the
+/// reassembler. `interface` is the device the packet was captured on, so
+/// output stays attributable when `--all-interfaces` merges several devices
+/// onto one stream of transactions. `resolver`, when `--resolve` is set, is
+/// consulted for the remote endpoint's hostname. `attributor`, when
+/// `--attribute-process` is set, is consulted for the local endpoint's
+/// owning process.
+fn process_packet(
+    packet_data: &[u8],
+    linktype: pcap::Linktype,
+    routine: &Routine,
+    reassembler: &mut Reassembler,
+    sink: &mut OutputSink,
+    scope: Option<&mut ProcessScope>,
+    interface: &str,
+    resolver: Option<&Resolver>,
+    attributor: Option<&mut SocketAttributor>,
+) {
+    reassembler.evict_idle();
+
+    let Some(decoded) = capture::decode_tcp_packet(linktype, packet_data) else {
+        return;
+    };
+
+    let src = (decoded.src_addr, decoded.src_port);
+    let dst = (decoded.dst_addr, decoded.dst_port);
+
+    if let Some(scope) = scope {
+        if !scope.owns(src, dst) {
+            return;
         }
-        HttpRequest::Response {
-            status, headers, ..
-        } => {
-            println!("📥 HTTP/{}", status);
-            for (key, value) in headers {
-                println!("   {}: {}", key, value);
+    }
+
+    let flow = FlowKey::new(decoded.src_addr, decoded.src_port, decoded.dst_addr, decoded.dst_port);
+
+    let segment = TcpSegment {
+        src_addr: decoded.src_addr,
+        src_port: decoded.src_port,
+        dst_addr: decoded.dst_addr,
+        dst_port: decoded.dst_port,
+        seq: decoded.seq,
+        flags: decoded.flags,
+        payload: decoded.payload.to_vec(),
+    };
+
+    for message in reassembler.ingest(segment) {
+        let data = String::from_utf8_lossy(&message.data);
+        if let Some(request) = http_parser::parse_http_data(&data, message.is_outgoing) {
+            if routine.evaluate_http(&request) == Verdict::Drop {
+                continue;
             }
-            println!();
+
+            // The remote endpoint is whichever side isn't the local socket:
+            // the destination for an outgoing request, the source for an
+            // incoming response. The local endpoint - the one a process on
+            // this machine actually owns - is the other one.
+            let (remote_addr, local) = match request {
+                http_parser::HttpRequest::Request { .. } => (dst.0, src),
+                http_parser::HttpRequest::Response { .. } => (src.0, dst),
+            };
+            let remote_host = resolver.and_then(|r| r.lookup(remote_addr));
+            let process = attributor.and_then(|a| a.lookup(local.0, local.1));
+
+            sink.record(flow, &request, src, dst, interface, remote_host.as_deref(), process.as_ref());
         }
     }
 }
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::net::IpAddr;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::capture::{FlowKey, ProcessInfo};
+use crate::http_parser::HttpRequest;
+
+/// Output format for captured HTTP transactions (`--output`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Har,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "har" => Ok(OutputFormat::Har),
+            other => Err(format!("unknown output format '{}', expected 'text', 'json' or 'har'", other)),
+        }
+    }
+}
+
+/// A request still waiting for its response on a given [`FlowKey`], held
+/// just long enough to be paired up into a HAR entry.
+struct PendingRequest {
+    started_ms: u128,
+    started_at: Instant,
+    method: String,
+    url: String,
+    headers: HashMap<String, String>,
+    body: Option<String>,
+}
+
+/// Receives every parsed HTTP request/response as it comes off the wire and
+/// renders it according to the configured [`OutputFormat`]: printed
+/// immediately for `text`/`json`, or buffered into HAR entries (paired
+/// request-then-response, per connection) and written out once capture
+/// stops.
+pub struct OutputSink {
+    format: OutputFormat,
+    har_path: Option<String>,
+    pending: HashMap<FlowKey, PendingRequest>,
+    entries: Vec<HarEntry>,
+}
+
+impl OutputSink {
+    pub fn new(format: OutputFormat, har_path: Option<String>) -> Self {
+        Self {
+            format,
+            har_path,
+            pending: HashMap::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Record one parsed HTTP message captured between `src` and `dst` on
+    /// the reassembled stream identified by `flow`, observed on `interface`
+    /// (relevant once more than one is captured at a time, e.g.
+    /// `--all-interfaces`). `remote_host`, when `--resolve` is enabled and
+    /// the reverse lookup for the remote endpoint has completed, is the
+    /// hostname to annotate the transaction with. `process`, when
+    /// `--attribute-process` is enabled and the local socket's owner was
+    /// found, is the process that owns the local side of the connection.
+    pub fn record(
+        &mut self,
+        flow: FlowKey,
+        request: &HttpRequest,
+        src: (IpAddr, u16),
+        dst: (IpAddr, u16),
+        interface: &str,
+        remote_host: Option<&str>,
+        process: Option<&ProcessInfo>,
+    ) {
+        match self.format {
+            OutputFormat::Text => print_text(request, src, dst, interface, remote_host, process),
+            OutputFormat::Json => print_json(request, src, dst, interface, remote_host, process),
+            OutputFormat::Har => self.record_har(flow, request),
+        }
+    }
+
+    fn record_har(&mut self, flow: FlowKey, request: &HttpRequest) {
+        match request {
+            HttpRequest::Request { method, url, headers, body } => {
+                self.pending.insert(
+                    flow,
+                    PendingRequest {
+                        started_ms: epoch_millis(),
+                        started_at: Instant::now(),
+                        method: method.clone(),
+                        url: url.clone(),
+                        headers: headers.clone(),
+                        body: body.clone(),
+                    },
+                );
+            }
+            HttpRequest::Response { status, headers, body } => {
+                let Some(pending) = self.pending.remove(&flow) else {
+                    log::debug!("Dropping HTTP response with no matching request on its stream");
+                    return;
+                };
+
+                let elapsed_ms = pending.started_at.elapsed().as_millis();
+                self.entries.push(HarEntry::new(pending, status, headers, body.as_deref(), elapsed_ms));
+            }
+        }
+    }
+
+    /// Write the accumulated HAR log to `har_path`. A no-op for `text`/`json`
+    /// formats. Called once, when capture stops.
+    pub fn finish(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let OutputFormat::Har = self.format else {
+            return Ok(());
+        };
+        let Some(path) = &self.har_path else {
+            return Ok(());
+        };
+
+        let log = HarLog {
+            log: HarLogInner {
+                version: "1.2",
+                creator: HarCreator { name: "httptrace", version: env!("CARGO_PKG_VERSION") },
+                entries: &self.entries,
+            },
+        };
+
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, &log)?;
+        Ok(())
+    }
+}
+
+fn print_text(
+    request: &HttpRequest,
+    src: (IpAddr, u16),
+    dst: (IpAddr, u16),
+    interface: &str,
+    remote_host: Option<&str>,
+    process: Option<&ProcessInfo>,
+) {
+    let mut endpoint = format!("{}:{} -> {}:{} on {}", src.0, src.1, dst.0, dst.1, interface);
+    if let Some(host) = remote_host {
+        endpoint.push_str(&format!(", remote: {}", host));
+    }
+    if let Some(process) = process {
+        endpoint.push_str(&format!(", process: {} ({})", process.name, process.pid));
+    }
+
+    match request {
+        HttpRequest::Request { method, url, headers, .. } => {
+            println!("🚀 {} {} [{}]", method, url, endpoint);
+            for (key, value) in headers {
+                println!("   {}: {}", key, value);
+            }
+            println!();
+        }
+        HttpRequest::Response { status, headers, .. } => {
+            println!("📥 HTTP/{} [{}]", status, endpoint);
+            for (key, value) in headers {
+                println!("   {}: {}", key, value);
+            }
+            println!();
+        }
+    }
+}
+
+/// One newline-delimited JSON record: either a request or a response, never
+/// both, with the fields that don't apply omitted.
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    kind: &'static str,
+    timestamp_ms: u128,
+    src_addr: IpAddr,
+    src_port: u16,
+    dst_addr: IpAddr,
+    dst_port: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    method: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<&'a str>,
+    headers: &'a HashMap<String, String>,
+    interface: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_host: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    process_pid: Option<u32>,
+}
+
+fn print_json(
+    request: &HttpRequest,
+    src: (IpAddr, u16),
+    dst: (IpAddr, u16),
+    interface: &str,
+    remote_host: Option<&str>,
+    process: Option<&ProcessInfo>,
+) {
+    let (kind, method, url, status, headers) = match request {
+        HttpRequest::Request { method, url, headers, .. } => ("request", Some(method.as_str()), Some(url.as_str()), None, headers),
+        HttpRequest::Response { status, headers, .. } => ("response", None, None, Some(status.as_str()), headers),
+    };
+
+    let record = JsonRecord {
+        kind,
+        timestamp_ms: epoch_millis(),
+        src_addr: src.0,
+        src_port: src.1,
+        dst_addr: dst.0,
+        dst_port: dst.1,
+        method,
+        url,
+        status,
+        headers,
+        interface,
+        remote_host,
+        process_name: process.map(|p| p.name.as_str()),
+        process_pid: process.map(|p| p.pid),
+    };
+
+    match serde_json::to_string(&record) {
+        Ok(line) => println!("{}", line),
+        Err(e) => log::warn!("Failed to serialize HTTP transaction: {}", e),
+    }
+}
+
+#[derive(Serialize)]
+struct HarLog<'a> {
+    log: HarLogInner<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarLogInner<'a> {
+    version: &'static str,
+    creator: HarCreator,
+    entries: &'a [HarEntry],
+}
+
+#[derive(Serialize)]
+struct HarCreator {
+    name: &'static str,
+    version: &'static str,
+}
+
+#[derive(Serialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+fn har_headers(headers: &HashMap<String, String>) -> Vec<HarHeader> {
+    headers
+        .iter()
+        .map(|(name, value)| HarHeader { name: name.clone(), value: value.clone() })
+        .collect()
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: &'static str,
+    headers: Vec<HarHeader>,
+    query_string: Vec<HarHeader>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+struct HarContent {
+    size: usize,
+    #[serde(rename = "mimeType")]
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: String,
+    http_version: &'static str,
+    headers: Vec<HarHeader>,
+    content: HarContent,
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Serialize)]
+struct HarTimings {
+    send: i64,
+    wait: i64,
+    receive: i64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: String,
+    time: u128,
+    request: HarRequest,
+    response: HarResponse,
+    cache: HarCache,
+    timings: HarTimings,
+}
+
+#[derive(Serialize)]
+struct HarCache {}
+
+impl HarEntry {
+    fn new(pending: PendingRequest, status: &str, response_headers: &HashMap<String, String>, response_body: Option<&str>, elapsed_ms: u128) -> Self {
+        let (status_code, status_text) = status.split_once(' ').unwrap_or((status, ""));
+        let status_code: u16 = status_code.parse().unwrap_or(0);
+
+        let mime_type = response_headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+            .map(|(_, v)| v.clone())
+            .unwrap_or_else(|| "application/octet-stream".to_string());
+
+        HarEntry {
+            started_date_time: format_iso8601(pending.started_ms),
+            time: elapsed_ms,
+            request: HarRequest {
+                method: pending.method,
+                url: pending.url,
+                http_version: "HTTP/1.1",
+                headers: har_headers(&pending.headers),
+                query_string: Vec::new(),
+                headers_size: -1,
+                body_size: pending.body.as_ref().map(|b| b.len() as i64).unwrap_or(-1),
+            },
+            response: HarResponse {
+                status: status_code,
+                status_text: status_text.to_string(),
+                http_version: "HTTP/1.1",
+                headers: har_headers(response_headers),
+                content: HarContent {
+                    size: response_body.map(|b| b.len()).unwrap_or(0),
+                    mime_type,
+                    text: response_body.map(|b| b.to_string()),
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: response_body.map(|b| b.len() as i64).unwrap_or(-1),
+            },
+            cache: HarCache {},
+            timings: HarTimings { send: 0, wait: elapsed_ms as i64, receive: 0 },
+        }
+    }
+}
+
+fn epoch_millis() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis()
+}
+
+/// Format a Unix epoch-millisecond timestamp as the ISO-8601 string HAR's
+/// `startedDateTime` requires (`YYYY-MM-DDTHH:MM:SS.sssZ`), computed by hand
+/// since pulling in a full date/time crate for one format call isn't worth it.
+fn format_iso8601(epoch_ms: u128) -> String {
+    let epoch_s = (epoch_ms / 1000) as i64;
+    let ms = (epoch_ms % 1000) as u32;
+    let days = epoch_s.div_euclid(86_400);
+    let secs_of_day = epoch_s.rem_euclid(86_400);
+
+    let (year, month, day) = civil_from_days(days);
+    let hour = secs_of_day / 3600;
+    let minute = (secs_of_day % 3600) / 60;
+    let second = secs_of_day % 60;
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, hour, minute, second, ms)
+}
+
+/// Howard Hinnant's `civil_from_days`: convert a day count since the Unix
+/// epoch (1970-01-01) into a proleptic-Gregorian (year, month, day).
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = (z - era * 146_097) as u64;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let y = yoe as i64 + era * 400;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = if m <= 2 { y + 1 } else { y };
+    (year, m, d)
+}
@@ -0,0 +1,70 @@
+use prettytable::{row, Table};
+
+use crate::capture::interface_detection::RouteInfo;
+use crate::capture::model::NetworkInterface;
+
+/// Output format for `list-interfaces`/`list-routes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(ReportFormat::Table),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!("unknown format '{}', expected 'table' or 'json'", other)),
+        }
+    }
+}
+
+/// Render the detected interfaces as either an aligned table or JSON.
+pub fn print_interfaces(interfaces: &[NetworkInterface], format: ReportFormat) {
+    match format {
+        ReportFormat::Table => {
+            let mut table = Table::new();
+            table.add_row(row!["NAME", "TYPE", "ADMIN", "OPER", "ENABLED", "DESCRIPTION"]);
+
+            for iface in interfaces {
+                table.add_row(row![
+                    iface.machine_name,
+                    format!("{:?}", iface.interface_type),
+                    format!("{:?}", iface.admin_state),
+                    format!("{:?}", iface.oper_state),
+                    iface.enabled,
+                    iface.description.as_deref().unwrap_or("-"),
+                ]);
+            }
+
+            table.printstd();
+        }
+        ReportFormat::Json => match serde_json::to_string_pretty(interfaces) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to serialize interfaces: {}", e),
+        },
+    }
+}
+
+/// Render the parsed routing table as either an aligned table or JSON.
+pub fn print_routes(routes: &[RouteInfo], format: ReportFormat) {
+    match format {
+        ReportFormat::Table => {
+            let mut table = Table::new();
+            table.add_row(row!["DESTINATION", "GATEWAY", "INTERFACE", "FLAGS"]);
+
+            for route in routes {
+                table.add_row(row![route.destination, route.gateway, route.interface, route.flags]);
+            }
+
+            table.printstd();
+        }
+        ReportFormat::Json => match serde_json::to_string_pretty(routes) {
+            Ok(json) => println!("{}", json),
+            Err(e) => eprintln!("Failed to serialize routes: {}", e),
+        },
+    }
+}
@@ -0,0 +1,69 @@
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use dns_lookup::lookup_addr;
+use log::debug;
+
+/// Cached reverse-DNS results plus the set of addresses a lookup has already
+/// been queued for, so a burst of packets for the same peer only triggers
+/// one lookup.
+struct Shared {
+    hostnames: HashMap<IpAddr, String>,
+    pending: HashSet<IpAddr>,
+}
+
+/// Resolves remote IPs to hostnames for `--resolve`, via a background
+/// thread so a slow or unreachable DNS server never blocks the capture
+/// loop. Results are cached forever once resolved; `lookup` never blocks,
+/// returning `None` until the background resolution for that address
+/// completes.
+pub struct Resolver {
+    shared: Arc<Mutex<Shared>>,
+    requests: mpsc::Sender<IpAddr>,
+}
+
+impl Resolver {
+    /// Spawn the background resolver thread.
+    pub fn spawn() -> Self {
+        let shared = Arc::new(Mutex::new(Shared {
+            hostnames: HashMap::new(),
+            pending: HashSet::new(),
+        }));
+        let (requests, rx) = mpsc::channel::<IpAddr>();
+        let worker_shared = shared.clone();
+
+        thread::spawn(move || {
+            for addr in rx {
+                let hostname = lookup_addr(&addr).unwrap_or_else(|e| {
+                    debug!("Reverse DNS lookup failed for {}: {}", addr, e);
+                    addr.to_string()
+                });
+
+                let mut shared = worker_shared.lock().unwrap();
+                shared.pending.remove(&addr);
+                shared.hostnames.insert(addr, hostname);
+            }
+        });
+
+        Self { shared, requests }
+    }
+
+    /// Return `addr`'s resolved hostname if the lookup has already
+    /// completed (the address itself, as text, if the reverse lookup
+    /// failed). The first call for a given address queues a background
+    /// lookup and returns `None`; later calls return the cached result.
+    pub fn lookup(&self, addr: IpAddr) -> Option<String> {
+        let mut shared = self.shared.lock().unwrap();
+        if let Some(hostname) = shared.hostnames.get(&addr) {
+            return Some(hostname.clone());
+        }
+
+        if shared.pending.insert(addr) {
+            let _ = self.requests.send(addr);
+        }
+
+        None
+    }
+}